@@ -0,0 +1,54 @@
+// `cargo bench` times move generation, run it before and after touching it. it's a plain timed
+// loop so it works without any extra crates
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use chess::game::Game;
+
+const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+// runs f until a second has passed, at least once, and returns the average time per run
+fn time<T>(mut f: impl FnMut() -> T) -> Duration {
+    let start = Instant::now();
+    let mut runs = 0;
+    while runs == 0 || start.elapsed() < Duration::from_secs(1) {
+        black_box(f());
+        runs += 1;
+    }
+    start.elapsed() / runs
+}
+
+fn main() {
+    for (name, fen) in [
+        ("startpos", Game::default().to_fen()),
+        ("kiwipete", KIWIPETE.to_string()),
+    ] {
+        let mut game = Game::from_fen(&fen).expect("benchmark fens are valid");
+        let per_call = time(|| game.compute_legal_moves(true));
+        println!("{} compute_legal_moves: {:?}", name, per_call);
+        // the incremental update after a move, undone so every run starts from the same place
+        let first = game
+            .all_legal_moves()
+            .next()
+            .expect("not a finished position")
+            .clone();
+        let per_move = time(|| {
+            game.request_move(&first.from, &first.to);
+            game.unmake_move_and_recalculate();
+        });
+        println!("{} move and undo: {:?}", name, per_move);
+        // kiwipete branches much wider, a shallower perft takes about as long
+        let depth = if name == "kiwipete" { 4 } else { 5 };
+        let start = Instant::now();
+        let nodes = game.perft(depth);
+        let elapsed = start.elapsed();
+        println!(
+            "{} perft({}): {} nodes in {:?}, {:.0} nodes/s",
+            name,
+            depth,
+            nodes,
+            elapsed,
+            nodes as f64 / elapsed.as_secs_f64()
+        );
+    }
+}
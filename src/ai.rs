@@ -0,0 +1,703 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::game::{Color, Game, Move, PieceType, Variant};
+use crate::opening_book::OpeningBook;
+
+// bigger than any material score, so mates always win out
+const MATE: i32 = 1_000_000;
+
+// positional bonuses in centipawns, from white's side with row 0 being rank 8 like the board.
+// black looks them up mirrored vertically
+type Table = [[i32; 8]; 8];
+
+const PAWN_TABLE: Table = [
+    [0, 0, 0, 0, 0, 0, 0, 0],
+    [50, 50, 50, 50, 50, 50, 50, 50],
+    [10, 10, 20, 30, 30, 20, 10, 10],
+    [5, 5, 10, 25, 25, 10, 5, 5],
+    [0, 0, 0, 20, 20, 0, 0, 0],
+    [5, -5, -10, 0, 0, -10, -5, 5],
+    [5, 10, 10, -20, -20, 10, 10, 5],
+    [0, 0, 0, 0, 0, 0, 0, 0],
+];
+const KNIGHT_TABLE: Table = [
+    [-50, -40, -30, -30, -30, -30, -40, -50],
+    [-40, -20, 0, 0, 0, 0, -20, -40],
+    [-30, 0, 10, 15, 15, 10, 0, -30],
+    [-30, 5, 15, 20, 20, 15, 5, -30],
+    [-30, 0, 15, 20, 20, 15, 0, -30],
+    [-30, 5, 10, 15, 15, 10, 5, -30],
+    [-40, -20, 0, 5, 5, 0, -20, -40],
+    [-50, -40, -30, -30, -30, -30, -40, -50],
+];
+const BISHOP_TABLE: Table = [
+    [-20, -10, -10, -10, -10, -10, -10, -20],
+    [-10, 0, 0, 0, 0, 0, 0, -10],
+    [-10, 0, 5, 10, 10, 5, 0, -10],
+    [-10, 5, 5, 10, 10, 5, 5, -10],
+    [-10, 0, 10, 10, 10, 10, 0, -10],
+    [-10, 10, 10, 10, 10, 10, 10, -10],
+    [-10, 5, 0, 0, 0, 0, 5, -10],
+    [-20, -10, -10, -10, -10, -10, -10, -20],
+];
+const ROOK_TABLE: Table = [
+    [0, 0, 0, 0, 0, 0, 0, 0],
+    [5, 10, 10, 10, 10, 10, 10, 5],
+    [-5, 0, 0, 0, 0, 0, 0, -5],
+    [-5, 0, 0, 0, 0, 0, 0, -5],
+    [-5, 0, 0, 0, 0, 0, 0, -5],
+    [-5, 0, 0, 0, 0, 0, 0, -5],
+    [-5, 0, 0, 0, 0, 0, 0, -5],
+    [0, 0, 0, 5, 5, 0, 0, 0],
+];
+const QUEEN_TABLE: Table = [
+    [-20, -10, -10, -5, -5, -10, -10, -20],
+    [-10, 0, 0, 0, 0, 0, 0, -10],
+    [-10, 0, 5, 5, 5, 5, 0, -10],
+    [-5, 0, 5, 5, 5, 5, 0, -5],
+    [0, 0, 5, 5, 5, 5, 0, -5],
+    [-10, 5, 5, 5, 5, 5, 0, -10],
+    [-10, 0, 5, 0, 0, 0, 0, -10],
+    [-20, -10, -10, -5, -5, -10, -10, -20],
+];
+// middlegame, the king stays tucked away behind its pawns
+const KING_TABLE: Table = [
+    [-30, -40, -40, -50, -50, -40, -40, -30],
+    [-30, -40, -40, -50, -50, -40, -40, -30],
+    [-30, -40, -40, -50, -50, -40, -40, -30],
+    [-30, -40, -40, -50, -50, -40, -40, -30],
+    [-20, -30, -30, -40, -40, -30, -30, -20],
+    [-10, -20, -20, -20, -20, -20, -20, -10],
+    [20, 20, 0, 0, 0, 0, 20, 20],
+    [20, 30, 10, 0, 0, 10, 30, 20],
+];
+
+fn table(piece_type: PieceType) -> &'static Table {
+    match piece_type {
+        PieceType::Pawn => &PAWN_TABLE,
+        PieceType::Knight => &KNIGHT_TABLE,
+        PieceType::Bishop => &BISHOP_TABLE,
+        PieceType::Rook => &ROOK_TABLE,
+        PieceType::Queen => &QUEEN_TABLE,
+        PieceType::King => &KING_TABLE,
+    }
+}
+
+// material and piece placement from the point of view of the side to move
+pub fn evaluate(game: &Game) -> i32 {
+    let mut score = 0;
+    for ((row, col), piece) in game.board.pieces() {
+        let (row, col) = (row as usize, col as usize);
+        let table = table(piece.piece_type);
+        match piece.color {
+            Color::White => score += piece.piece_type.value() + table[row][col],
+            Color::Black => score -= piece.piece_type.value() + table[7 - row][col],
+        }
+    }
+    match game.turn {
+        Color::White => score,
+        Color::Black => -score,
+    }
+}
+
+// variant wins always go to the side that just moved
+fn lost_by_variant(game: &Game) -> bool {
+    game.variant != Variant::Standard && game.variant_result().is_some()
+}
+
+fn legal_moves(game: &Game) -> Vec<Move> {
+    game.all_legal_moves().cloned().collect()
+}
+
+// outside any real score, and still safe to negate unlike i32::MIN
+const INFINITY: i32 = i32::MAX;
+
+// how a stored score relates to the real one, alpha-beta cutoffs only give bounds
+#[derive(Clone, Copy, PartialEq)]
+enum Bound {
+    Exact,
+    // the real score is at least this, the node failed high
+    Lower,
+    // the real score is at most this, every move failed low
+    Upper,
+}
+
+struct TtEntry {
+    depth: u8,
+    score: i32,
+    bound: Bound,
+    best_move: Option<Move>,
+}
+
+// state shared by every node of one search
+struct Search {
+    // give up once this passes, None searches to the full depth
+    deadline: Option<Instant>,
+    // set when the deadline hit, every score after that is garbage
+    stopped: bool,
+    // positions already searched, by zobrist hash. kept between iterative deepening passes
+    table: HashMap<u64, TtEntry>,
+    // positions visited, quiescence included
+    nodes: u64,
+    // always on for real searches, the tests turn them off to see what they save
+    use_table: bool,
+    use_ordering: bool,
+}
+
+impl Search {
+    fn new(deadline: Option<Instant>) -> Search {
+        Search {
+            deadline,
+            stopped: false,
+            table: HashMap::new(),
+            nodes: 0,
+            use_table: true,
+            use_ordering: true,
+        }
+    }
+
+    fn out_of_time(&mut self) -> bool {
+        if !self.stopped && self.deadline.is_some_and(|d| Instant::now() >= d) {
+            self.stopped = true;
+        }
+        self.stopped
+    }
+
+    // alpha-beta cuts off sooner when good moves come first. captures go first, biggest victim
+    // taken by the cheapest piece, then the best move found last time in this position
+    fn order_moves(&self, game: &Game, moves: &mut [Move]) {
+        if !self.use_ordering {
+            return;
+        }
+        let table_move = self
+            .table
+            .get(&game.hash())
+            .and_then(|entry| entry.best_move.as_ref());
+        // (group, order within the group), highest first. sorting is stable so everything else
+        // keeps generation order
+        moves.sort_by_cached_key(|mov| {
+            let priority = if let Some(victim) = mov.capture {
+                let mover = game
+                    .piece_at_square(&mov.from)
+                    .map_or(0, |p| p.piece_type.value());
+                (2, victim.value() - mover)
+            } else if table_move.is_some_and(|m| {
+                m.from == mov.from && m.to == mov.to && m.promotion == mov.promotion
+            }) {
+                (1, 0)
+            } else {
+                (0, 0)
+            };
+            std::cmp::Reverse(priority)
+        });
+    }
+
+    // expects game.legal_moves to be up to date, leaves them stale.
+    // scores outside alpha..beta are only bounds, the caller doesn't care about them
+    fn negamax(&mut self, game: &mut Game, depth: u8, mut alpha: i32, beta: i32) -> i32 {
+        if self.out_of_time() {
+            return 0;
+        }
+        self.nodes += 1;
+        // lost even with moves left, scored like a mate
+        if lost_by_variant(game) {
+            return -MATE - depth as i32;
+        }
+        // a search at least this deep already happened from this position
+        if let Some(entry) = self.table.get(&game.hash()).filter(|_| self.use_table) {
+            if entry.depth >= depth {
+                match entry.bound {
+                    Bound::Exact => return entry.score,
+                    Bound::Lower if entry.score >= beta => return entry.score,
+                    Bound::Upper if entry.score <= alpha => return entry.score,
+                    _ => {}
+                }
+            }
+        }
+        let mut moves = legal_moves(game);
+        if moves.is_empty() {
+            // a mate found with more depth left is a quicker mate
+            return if game.is_in_check(game.turn) {
+                -MATE - depth as i32
+            } else {
+                0
+            };
+        }
+        if depth == 0 {
+            return self.quiescence(game, alpha, beta);
+        }
+        self.order_moves(game, &mut moves);
+        let original_alpha = alpha;
+        let mut best = -INFINITY;
+        let mut best_move = None;
+        for mov in moves {
+            game.make_move(&mov);
+            game.compute_legal_moves(true);
+            let score = -self.negamax(game, depth - 1, -beta, -alpha);
+            game.unmake_move();
+            if score > best {
+                best = score;
+                best_move = Some(mov);
+            }
+            alpha = alpha.max(score);
+            // the opponent already has something better than this line, they won't allow it
+            if alpha >= beta {
+                break;
+            }
+        }
+        if !self.stopped && self.use_table {
+            let bound = if best <= original_alpha {
+                Bound::Upper
+            } else if best >= beta {
+                Bound::Lower
+            } else {
+                Bound::Exact
+            };
+            self.table.insert(
+                game.hash(),
+                TtEntry {
+                    depth,
+                    score: best,
+                    bound,
+                    best_move,
+                },
+            );
+        }
+        best
+    }
+
+    // keeps searching captures past the depth limit so the evaluation never lands in the middle
+    // of an exchange. same contract as negamax
+    fn quiescence(&mut self, game: &mut Game, mut alpha: i32, beta: i32) -> i32 {
+        if self.out_of_time() {
+            return 0;
+        }
+        self.nodes += 1;
+        if lost_by_variant(game) {
+            return -MATE;
+        }
+        // the side to move doesn't have to capture, so the static score is a lower bound
+        let stand_pat = evaluate(game);
+        if stand_pat >= beta {
+            return stand_pat;
+        }
+        alpha = alpha.max(stand_pat);
+        let mut best = stand_pat;
+        let mut captures = game.capture_moves();
+        self.order_moves(game, &mut captures);
+        for mov in captures {
+            game.make_move(&mov);
+            game.compute_legal_moves(true);
+            let score = -self.quiescence(game, -beta, -alpha);
+            game.unmake_move();
+            best = best.max(score);
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                break;
+            }
+        }
+        best
+    }
+
+    // best move and its score, None if there are no legal moves
+    fn root(&mut self, game: &mut Game, depth: u8) -> Option<(i32, Move)> {
+        let mut best: Option<(i32, Move)> = None;
+        let mut alpha = -INFINITY;
+        let mut moves = legal_moves(game);
+        self.order_moves(game, &mut moves);
+        for mov in moves {
+            game.make_move(&mov);
+            game.compute_legal_moves(true);
+            let score = -self.negamax(game, depth.saturating_sub(1), -INFINITY, -alpha);
+            game.unmake_move();
+            if best.is_none() || score > alpha {
+                alpha = score;
+                best = Some((score, mov));
+            }
+        }
+        // so the next iterative deepening pass looks at this move early
+        if let Some((score, mov)) = best.as_ref().filter(|_| !self.stopped && self.use_table) {
+            self.table.insert(
+                game.hash(),
+                TtEntry {
+                    depth,
+                    score: *score,
+                    bound: Bound::Exact,
+                    best_move: Some(mov.clone()),
+                },
+            );
+        }
+        // the search leaves legal_moves from somewhere down the tree
+        game.compute_legal_moves(true);
+        best
+    }
+}
+
+// a move from the embedded opening book, played from theory without searching. standard chess
+// only, the variants' openings go differently
+fn book_move(game: &Game) -> Option<Move> {
+    if game.variant != Variant::Standard {
+        return None;
+    }
+    // any number that changes from game to game will do, so the computer doesn't always play
+    // the same opening
+    let roll = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos() as u64);
+    OpeningBook::embedded().pick(game, roll)
+}
+
+// None if the side to move has no legal moves
+pub fn best_move(game: &mut Game, depth: u8) -> Option<Move> {
+    if let Some(mov) = book_move(game) {
+        return Some(mov);
+    }
+    let mut search = Search::new(None);
+    search.root(game, depth).map(|(_, mov)| mov)
+}
+
+// searches one ply deeper at a time until the time runs out. the result of the deepest search
+// that finished and its depth
+fn deepen(search: &mut Search, game: &mut Game) -> (Option<Move>, u8) {
+    // if not even depth 1 finishes there's still something to play
+    let mut best = legal_moves(game).into_iter().next();
+    let mut reached = 0;
+    for depth in 1..=u8::MAX {
+        let result = search.root(game, depth);
+        if search.stopped {
+            break;
+        }
+        match result {
+            Some((score, mov)) => {
+                best = Some(mov);
+                reached = depth;
+                // nothing deeper can improve on a forced mate
+                if score.abs() >= MATE {
+                    break;
+                }
+            }
+            None => break,
+        }
+    }
+    (best, reached)
+}
+
+// plays the result of the deepest search that finishes in time
+pub fn best_move_timed(game: &mut Game, millis: u64) -> Option<Move> {
+    if let Some(mov) = book_move(game) {
+        return Some(mov);
+    }
+    let mut search = Search::new(Some(Instant::now() + Duration::from_millis(millis)));
+    deepen(&mut search, game).0
+}
+
+// the move a timed search picks and the replies it expects after it, as far as the deepest
+// finished search looked. skips the book, this is for watching the search think
+pub fn best_line_timed(game: &mut Game, millis: u64) -> Vec<Move> {
+    let mut search = Search::new(Some(Instant::now() + Duration::from_millis(millis)));
+    let (mut next, depth) = deepen(&mut search, game);
+    // after the first move the line is the table's best move in each position it reaches
+    let mut position = game.clone();
+    let mut line = vec![];
+    while let Some(mov) = next.filter(|_| line.len() < depth.max(1) as usize) {
+        position.make_move(&mov);
+        position.compute_legal_moves(true);
+        line.push(mov);
+        // a hash collision could hand back a move from some other position
+        next = search
+            .table
+            .get(&position.hash())
+            .and_then(|entry| entry.best_move.clone())
+            .filter(|m| position.all_legal_moves().any(|l| l == m));
+    }
+    line
+}
+
+// a played move that scored a lot worse than the search's own choice
+pub struct Mistake {
+    // how many moves had been played before it
+    pub ply: usize,
+    pub played: Move,
+    // what the search would have played instead
+    pub better: Move,
+    // how much the played move threw away in centipawns, from the mover's side. capped at
+    // MATE_SWING
+    pub swing: i32,
+}
+
+// missing a mate or walking into one, mate scores are too big to mean anything in centipawns
+pub const MATE_SWING: i32 = 10_000;
+
+// searches the position before every move of the game to depth and flags the moves that scored
+// at least threshold worse than the best one, in the order they were played
+pub fn find_mistakes(game: &Game, depth: u8, threshold: i32) -> Vec<Mistake> {
+    let mut position = game.clone();
+    position.go_to_ply(0);
+    // one table for the whole game, neighbouring positions share most of their trees
+    let mut search = Search::new(None);
+    let mut mistakes = vec![];
+    for (ply, played) in game.moves.iter().enumerate() {
+        let best = search.root(&mut position, depth);
+        position.make_move(played);
+        position.compute_legal_moves(true);
+        // the same depth the root gave each of its moves
+        let score = -search.negamax(&mut position, depth.saturating_sub(1), -INFINITY, INFINITY);
+        position.compute_legal_moves(true);
+        let Some((best, better)) = best else {
+            continue;
+        };
+        let swing = (best - score).min(MATE_SWING);
+        if swing >= threshold && better != *played {
+            mistakes.push(Mistake {
+                ply,
+                played: played.clone(),
+                better,
+                swing,
+            });
+        }
+    }
+    mistakes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::algebraic_to_square;
+
+    fn game(fen: &str) -> Game {
+        Game::from_fen(fen).unwrap()
+    }
+
+    // every line searched to the end with no pruning at all, the captures-only tail included.
+    // scored the same way as Search::negamax
+    fn plain_negamax(game: &mut Game, depth: u8) -> i32 {
+        let moves = legal_moves(game);
+        if moves.is_empty() {
+            return if game.is_in_check(game.turn) {
+                -MATE - depth as i32
+            } else {
+                0
+            };
+        }
+        if depth == 0 {
+            return plain_quiescence(game);
+        }
+        let mut best = -INFINITY;
+        for mov in moves {
+            game.make_move(&mov);
+            game.compute_legal_moves(true);
+            best = best.max(-plain_negamax(game, depth - 1));
+            game.unmake_move();
+            game.compute_legal_moves(true);
+        }
+        best
+    }
+
+    fn plain_quiescence(game: &mut Game) -> i32 {
+        let mut best = evaluate(game);
+        for mov in game.capture_moves() {
+            game.make_move(&mov);
+            game.compute_legal_moves(true);
+            best = best.max(-plain_quiescence(game));
+            game.unmake_move();
+            game.compute_legal_moves(true);
+        }
+        best
+    }
+
+    // the root move plain_negamax likes best, with its score
+    fn plain_best(game: &mut Game, depth: u8) -> (i32, Move) {
+        let mut best: Option<(i32, Move)> = None;
+        for mov in legal_moves(game) {
+            game.make_move(&mov);
+            game.compute_legal_moves(true);
+            let score = -plain_negamax(game, depth - 1);
+            game.unmake_move();
+            game.compute_legal_moves(true);
+            if best.as_ref().is_none_or(|(b, _)| score > *b) {
+                best = Some((score, mov));
+            }
+        }
+        best.unwrap()
+    }
+
+    #[test]
+    fn alpha_beta_matches_plain_negamax() {
+        for (fen, depth) in [
+            ("8/8/4k3/8/2p5/1P6/4K3/8 w - - 0 1", 3),
+            ("4k3/8/3p4/4r3/3N4/8/8/4K2R w K - 0 1", 3),
+            // mate on the back rank
+            ("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1", 2),
+        ] {
+            let (plain, _) = plain_best(&mut game(fen), depth);
+            // the table can hand back a score from a deeper search, so leave it out. ordering
+            // only changes what gets cut, never the score
+            for use_ordering in [false, true] {
+                let mut search = Search::new(None);
+                search.use_table = false;
+                search.use_ordering = use_ordering;
+                let (score, _) = search.root(&mut game(fen), depth).unwrap();
+                assert_eq!(score, plain, "{} depth {}", fen, depth);
+            }
+        }
+    }
+
+    #[test]
+    fn centralized_knight_scores_higher() {
+        let center = evaluate(&game("4k3/8/8/8/3N4/8/8/4K3 w - - 0 1"));
+        let rim = evaluate(&game("4k3/8/8/8/N7/8/8/4K3 w - - 0 1"));
+        assert!(center > rim, "{} vs {}", center, rim);
+        // same thing from black's side, the table is mirrored
+        let center = evaluate(&game("4k3/8/8/3n4/8/8/8/4K3 b - - 0 1"));
+        let rim = evaluate(&game("4k3/8/8/n7/8/8/8/4K3 b - - 0 1"));
+        assert!(center > rim, "{} vs {}", center, rim);
+        // and both colors score their mirror image the same
+        assert_eq!(
+            evaluate(&game("4k3/8/8/8/3N4/8/8/4K3 w - - 0 1")),
+            evaluate(&game("4k3/8/8/3n4/8/8/8/4K3 b - - 0 1"))
+        );
+    }
+
+    #[test]
+    fn quiescence_sees_the_recapture() {
+        // the d5 pawn is guarded by e6, taking it loses the queen for a pawn
+        let mut g = game("4k3/8/4p3/3p4/8/8/8/3QK3 w - - 0 1");
+        let d5 = algebraic_to_square("d5").unwrap();
+        // stopping at the static score after one ply grabs the pawn
+        let greedy = legal_moves(&g)
+            .into_iter()
+            .max_by_key(|mov| {
+                g.make_move(mov);
+                let score = -evaluate(&g);
+                g.unmake_move();
+                score
+            })
+            .unwrap();
+        g.compute_legal_moves(true);
+        assert_eq!(greedy.to, d5);
+        for depth in 1..=2 {
+            let mov = best_move(&mut g, depth).unwrap();
+            assert_ne!(mov.to, d5, "depth {} took the pawn", depth);
+        }
+    }
+
+    #[test]
+    fn timed_search_keeps_to_its_budget() {
+        // out of the book, so there's something to search
+        let mut g = game("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4");
+        for millis in [0, 200] {
+            let start = Instant::now();
+            let mov = best_move_timed(&mut g, millis).unwrap();
+            // the clock is checked at every node, anything past the budget is unwinding the search
+            let elapsed = start.elapsed();
+            assert!(
+                elapsed < Duration::from_millis(millis + 250),
+                "{}ms took {:?}",
+                millis,
+                elapsed
+            );
+            assert!(g.parse_uci(&mov.to_uci()).is_some());
+        }
+    }
+
+    #[test]
+    fn opens_from_the_book() {
+        let mut g = Game::default();
+        let book = ["e2e4", "d2d4", "c2c4", "g1f3"];
+        let mov = best_move(&mut g, 4).unwrap();
+        assert!(book.contains(&mov.to_uci().as_str()), "{}", mov.to_uci());
+        let mov = best_move_timed(&mut g, 0).unwrap();
+        assert!(book.contains(&mov.to_uci().as_str()), "{}", mov.to_uci());
+    }
+
+    #[test]
+    fn finds_the_blunder_into_mate() {
+        // scholar's mate, black's Nf6 lets the queen take f7
+        let mut g = Game::default();
+        for uci in ["e2e4", "e7e5", "f1c4", "b8c6", "d1h5", "g8f6", "h5f7"] {
+            let mov = g.parse_uci(uci).unwrap();
+            g.request_move_promoting(&mov.from, &mov.target(), mov.promotion);
+        }
+        assert!(g.is_checkmate());
+        let mistakes = find_mistakes(&g, 2, 150);
+        let mistake = mistakes.iter().find(|m| m.ply == 5).unwrap();
+        assert_eq!(mistake.played.to_uci(), "g8f6");
+        assert_eq!(mistake.swing, MATE_SWING);
+        // whatever it suggests has to stop the mate
+        let mut position = g.clone();
+        position.go_to_ply(5);
+        let better = mistake.better.clone();
+        position.request_move_promoting(&better.from, &better.target(), better.promotion);
+        assert!(position.all_legal_moves().all(|m| {
+            let mut after = position.clone();
+            after.request_move_promoting(&m.from, &m.target(), m.promotion);
+            !after.is_checkmate()
+        }));
+        assert!(mistakes
+            .iter()
+            .all(|m| m.swing >= 150 && m.better != m.played));
+        // the mating move itself is the best there is
+        assert!(mistakes.iter().all(|m| m.ply != 6));
+        // the game is left alone
+        assert_eq!(g.moves.len(), 7);
+        assert!(g.is_checkmate());
+    }
+
+    #[test]
+    fn best_line_is_playable() {
+        let mut g = game("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        let line: Vec<String> = best_line_timed(&mut g, 100)
+            .iter()
+            .map(|m| m.to_uci())
+            .collect();
+        // nothing comes after the mate
+        assert_eq!(line, ["a1a8"]);
+
+        let mut g = game("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4");
+        let before = g.hash();
+        let line = best_line_timed(&mut g, 200);
+        assert!(!line.is_empty());
+        assert_eq!(g.hash(), before);
+        for mov in line {
+            let played = g.parse_uci(&mov.to_uci()).unwrap();
+            g.request_move_promoting(&played.from, &played.target(), played.promotion);
+        }
+    }
+
+    // positions an iterative deepening search up to depth visits
+    fn nodes_searched(fen: &str, depth: u8, use_table: bool, use_ordering: bool) -> u64 {
+        let mut g = game(fen);
+        let mut search = Search::new(None);
+        search.use_table = use_table;
+        search.use_ordering = use_ordering;
+        for depth in 1..=depth {
+            search.root(&mut g, depth);
+        }
+        search.nodes
+    }
+
+    #[test]
+    fn table_saves_nodes() {
+        // king and pawn moves transpose into each other all the time
+        let fen = "8/8/4k3/8/2p5/1P6/4K3/8 w - - 0 1";
+        let with = nodes_searched(fen, 6, true, true);
+        let without = nodes_searched(fen, 6, false, true);
+        assert!(
+            with < without,
+            "{} with the table, {} without",
+            with,
+            without
+        );
+    }
+
+    #[test]
+    fn ordering_saves_nodes() {
+        // plenty of captures, good and bad. the table stays on since its best move is part of
+        // the ordering
+        let fen = "4k3/8/3p4/4r3/3N4/8/8/4K2R w K - 0 1";
+        let with = nodes_searched(fen, 4, true, true);
+        let without = nodes_searched(fen, 4, true, false);
+        assert!(with < without, "{} ordered, {} unordered", with, without);
+    }
+}
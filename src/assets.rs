@@ -0,0 +1,75 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+use macroquad::prelude::{load_ttf_font_from_bytes, Font, Image, ImageFormat, Texture2D};
+
+use crate::game::{Color, PieceType};
+
+const FONT: &[u8] = include_bytes!("../assets/Atkinson-Hyperlegible-Bold-102.ttf");
+
+// (color, piece type, file name for error messages, embedded file)
+macro_rules! piece_file {
+    ($color:ident, $piece_type:ident, $file:literal) => {
+        (
+            Color::$color,
+            PieceType::$piece_type,
+            $file,
+            include_bytes!(concat!("../assets/", $file)),
+        )
+    };
+}
+
+const PIECE_FILES: [(Color, PieceType, &str, &[u8]); 12] = [
+    piece_file!(Black, Bishop, "bB.png"),
+    piece_file!(Black, King, "bK.png"),
+    piece_file!(Black, Knight, "bN.png"),
+    piece_file!(Black, Pawn, "bP.png"),
+    piece_file!(Black, Queen, "bQ.png"),
+    piece_file!(Black, Rook, "bR.png"),
+    piece_file!(White, Bishop, "wB.png"),
+    piece_file!(White, King, "wK.png"),
+    piece_file!(White, Knight, "wN.png"),
+    piece_file!(White, Pawn, "wP.png"),
+    piece_file!(White, Queen, "wQ.png"),
+    piece_file!(White, Rook, "wR.png"),
+];
+
+pub struct Assets {
+    pub font: Font,
+    // one texture for every piece of both colors
+    pub pieces: HashMap<(Color, PieceType), Texture2D>,
+}
+
+#[derive(Debug)]
+pub struct AssetError {
+    // name of the embedded file that failed
+    pub asset: &'static str,
+    pub error: macroquad::Error,
+}
+
+impl Display for AssetError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "failed to load asset \"{}\": {}", self.asset, self.error)
+    }
+}
+
+fn load_texture(asset: &'static str, bytes: &[u8]) -> Result<Texture2D, AssetError> {
+    // decode to an image first, Texture2D::from_file_with_format panics on bad data
+    let image = Image::from_file_with_format(bytes, Some(ImageFormat::Png))
+        .map_err(|error| AssetError { asset, error })?;
+    Ok(Texture2D::from_image(&image))
+}
+
+pub fn load_assets() -> Result<Assets, AssetError> {
+    let mut pieces = HashMap::new();
+    for (color, piece_type, asset, bytes) in PIECE_FILES {
+        pieces.insert((color, piece_type), load_texture(asset, bytes)?);
+    }
+    Ok(Assets {
+        font: load_ttf_font_from_bytes(FONT).map_err(|error| AssetError {
+            asset: "Atkinson-Hyperlegible-Bold-102.ttf",
+            error,
+        })?,
+        pieces,
+    })
+}
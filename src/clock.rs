@@ -0,0 +1,73 @@
+use crate::game::Color;
+
+// both sides' remaining time in seconds, only the side to move counts down
+#[derive(Clone)]
+pub struct Clocks {
+    white: f32,
+    black: f32,
+    increment: f32,
+    // the side whose time ran out, the clocks stop then
+    flagged: Option<Color>,
+}
+
+impl Clocks {
+    pub fn new(base_seconds: f32, increment_seconds: f32) -> Self {
+        Clocks {
+            white: base_seconds,
+            black: base_seconds,
+            increment: increment_seconds,
+            flagged: None,
+        }
+    }
+    // parses a time control like "5+3", minutes then increment seconds
+    pub fn parse(s: &str) -> Option<Self> {
+        let (base, increment) = s.split_once('+').unwrap_or((s, "0"));
+        let base: f32 = base.parse().ok()?;
+        let increment: f32 = increment.parse().ok()?;
+        (base > 0f32 && increment >= 0f32).then(|| Clocks::new(base * 60f32, increment))
+    }
+    pub fn remaining(&self, color: Color) -> f32 {
+        match color {
+            Color::White => self.white,
+            Color::Black => self.black,
+        }
+    }
+    pub fn flagged(&self) -> Option<Color> {
+        self.flagged
+    }
+    fn time_mut(&mut self, color: Color) -> &mut f32 {
+        match color {
+            Color::White => &mut self.white,
+            Color::Black => &mut self.black,
+        }
+    }
+    // runs `color`'s clock down, flagging it at zero
+    pub fn tick(&mut self, color: Color, seconds: f32) {
+        if self.flagged.is_some() {
+            return;
+        }
+        let time = self.time_mut(color);
+        *time = (*time - seconds).max(0f32);
+        if *time == 0f32 {
+            self.flagged = Some(color);
+        }
+    }
+    // after `color` completes a move
+    pub fn add_increment(&mut self, color: Color) {
+        if self.flagged.is_none() {
+            let increment = self.increment;
+            *self.time_mut(color) += increment;
+        }
+    }
+}
+
+// m:ss, with tenths once it's under ten seconds
+pub fn format_time(seconds: f32) -> String {
+    if seconds < 10f32 {
+        // rounded down so it never shows 10.0
+        format!("0:{:04.1}", (seconds * 10f32).floor() / 10f32)
+    } else {
+        let seconds = seconds.ceil() as u32;
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
@@ -0,0 +1,102 @@
+use crate::game::{
+    Board, CastlingRights, Color, Game, Piece, PieceType, SetupError, Square, Variant,
+};
+
+// what the palette offers, white's pieces then black's, and an eraser at the end
+pub fn palette() -> Vec<Option<Piece>> {
+    let piece_types = [
+        PieceType::King,
+        PieceType::Queen,
+        PieceType::Rook,
+        PieceType::Bishop,
+        PieceType::Knight,
+        PieceType::Pawn,
+    ];
+    [Color::White, Color::Black]
+        .into_iter()
+        .flat_map(|color| piece_types.map(|piece_type| Some(Piece { piece_type, color })))
+        .chain([None])
+        .collect()
+}
+
+// a position being set up by hand, the game underneath is left alone until it's started
+pub struct Editor {
+    pub board: Board,
+    pub turn: Color,
+    pub castling_rights: CastlingRights,
+    // placed by clicking a square, None erases
+    pub brush: Option<Piece>,
+    // why the last start failed, cleared by the next edit
+    pub error: Option<SetupError>,
+}
+
+impl Editor {
+    // starts from the game's current position
+    pub fn new(game: &Game) -> Self {
+        Editor {
+            board: game.board,
+            turn: game.turn,
+            castling_rights: game.castling_rights,
+            brush: None,
+            error: None,
+        }
+    }
+    // placing the same piece again takes it off
+    pub fn place(&mut self, square: &Square) {
+        let piece = if *self.board.get(square) == self.brush {
+            None
+        } else {
+            self.brush
+        };
+        self.board.set(square, piece);
+        self.error = None;
+    }
+    pub fn clear(&mut self, square: &Square) {
+        self.board.set(square, None);
+        self.error = None;
+    }
+    // KQkq order, like in a fen
+    pub fn toggle_castling(&mut self, index: usize) {
+        let rights = &mut self.castling_rights;
+        let right = match index {
+            0 => &mut rights.white_kingside,
+            1 => &mut rights.white_queenside,
+            2 => &mut rights.black_kingside,
+            _ => &mut rights.black_queenside,
+        };
+        *right = !*right;
+        self.error = None;
+    }
+    pub fn castling_string(&self) -> String {
+        let rights = &self.castling_rights;
+        let castling: String = [
+            (rights.white_kingside, 'K'),
+            (rights.white_queenside, 'Q'),
+            (rights.black_kingside, 'k'),
+            (rights.black_queenside, 'q'),
+        ]
+        .iter()
+        .filter(|(allowed, _)| *allowed)
+        .map(|(_, c)| *c)
+        .collect();
+        if castling.is_empty() {
+            "-".to_owned()
+        } else {
+            castling
+        }
+    }
+    // a new game from the edited position, with castling rights the kings and rooks can't back
+    // up dropped like in a fen
+    pub fn start(&self, variant: Variant) -> Result<Game, SetupError> {
+        let pieces: Vec<(Square, Piece)> = self.board.pieces().collect();
+        let game = Game::from_pieces(&pieces, self.turn)?;
+        // from_pieces has no castling, so go through the fen with the rights filled in
+        let fen = game.to_fen();
+        let mut fields: Vec<&str> = fen.split(' ').collect();
+        let castling = self.castling_string();
+        fields[2] = &castling;
+        let mut game = Game::from_fen(&fields.join(" ")).expect("fen of a valid position");
+        game.set_variant(variant);
+        Ok(game)
+    }
+}
@@ -0,0 +1,151 @@
+use std::fmt::{Display, Formatter};
+
+use crate::ai;
+use crate::game::{FenError, Game, Move};
+
+// mate in two puzzles, the search needs 3 plies to see them
+pub const MATE_IN_TWO: [&str; 4] = [
+    "r2qkb1r/pp2nppp/3p4/2pNN1B1/2BnP3/3P4/PPP2PPP/R2bK2R w KQkq - bm Nf6+; id \"legal's mate\";",
+    "r1b2k1r/ppp1bppp/8/1B1Q4/5q2/2P5/PPP2PPP/R3R1K1 w - - bm Qd8+; id \"queen sacrifice\";",
+    "r3r1k1/ppp2ppp/2p5/5Q2/1b1q4/8/PPP1BPPP/R1B2K1R b - - bm Qd1+; id \"queen sacrifice, black\";",
+    "4kb1r/p2n1ppp/4q3/4p1B1/4P3/1Q6/PPP2PPP/2KR4 w k - bm Qb8+; id \"opera game\";",
+];
+
+#[derive(Debug, PartialEq)]
+pub enum EpdError {
+    // the four position fields, like the first four of a fen
+    FieldCount(usize),
+    Fen(FenError),
+    // without a bm operation there's nothing to check the search against
+    NoBestMove,
+    // a bm move that isn't legal in the position
+    BadBestMove(String),
+}
+
+impl Display for EpdError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EpdError::FieldCount(n) => write!(f, "expected 4 position fields, found {}", n),
+            EpdError::Fen(e) => write!(f, "invalid position: {}", e),
+            EpdError::NoBestMove => write!(f, "no bm operation"),
+            EpdError::BadBestMove(s) => write!(f, "invalid best move \"{}\"", s),
+        }
+    }
+}
+
+impl From<FenError> for EpdError {
+    fn from(e: FenError) -> Self {
+        EpdError::Fen(e)
+    }
+}
+
+pub struct EpdPosition {
+    pub game: Game,
+    // any of these counts as solving it
+    pub best_moves: Vec<Move>,
+    pub id: Option<String>,
+}
+
+impl EpdPosition {
+    // the fen fields without the clocks, then `opcode operands;` operations. only bm, id, hmvc
+    // and fmvn are read, the rest are skipped
+    pub fn parse(line: &str) -> Result<EpdPosition, EpdError> {
+        let fields: Vec<&str> = line.splitn(5, ' ').collect();
+        if fields.len() < 4 {
+            return Err(EpdError::FieldCount(fields.len()));
+        }
+        let operations: Vec<(&str, &str)> = fields
+            .get(4)
+            .map_or("", |o| *o)
+            .split(';')
+            .map(str::trim)
+            .filter(|o| !o.is_empty())
+            .map(|o| o.split_once(' ').unwrap_or((o, "")))
+            .collect();
+        let operand = |opcode: &str| {
+            operations
+                .iter()
+                .find(|(op, _)| *op == opcode)
+                .map(|(_, operand)| operand.trim())
+        };
+        let game = Game::from_fen(&format!(
+            "{} {} {}",
+            fields[..4].join(" "),
+            operand("hmvc").unwrap_or("0"),
+            operand("fmvn").unwrap_or("1")
+        ))?;
+        let best_moves = operand("bm")
+            .ok_or(EpdError::NoBestMove)?
+            .split_whitespace()
+            .map(|san| {
+                game.parse_san(san)
+                    .ok_or_else(|| EpdError::BadBestMove(san.to_string()))
+            })
+            .collect::<Result<Vec<Move>, EpdError>>()?;
+        Ok(EpdPosition {
+            game,
+            best_moves,
+            id: operand("id").map(|id| id.trim_matches('"').to_string()),
+        })
+    }
+    // what the search plays at this depth, and whether it's one of the best moves
+    pub fn solve(&mut self, depth: u8) -> (Option<Move>, bool) {
+        let found = ai::best_move(&mut self.game, depth);
+        let solved = found.as_ref().is_some_and(|m| self.best_moves.contains(m));
+        (found, solved)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mate_in_two_is_solved() {
+        for line in MATE_IN_TWO {
+            let mut position = EpdPosition::parse(line).unwrap();
+            let (found, solved) = position.solve(3);
+            assert!(
+                solved,
+                "{:?} played {:?}",
+                position.id,
+                found.map(|m| m.to_uci())
+            );
+        }
+    }
+
+    #[test]
+    fn parses_operations() {
+        let position = EpdPosition::parse(MATE_IN_TWO[3]).unwrap();
+        assert_eq!(position.id.as_deref(), Some("opera game"));
+        assert_eq!(position.best_moves.len(), 1);
+        assert_eq!(position.best_moves[0].to_uci(), "b3b8");
+        let position = EpdPosition::parse(
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 bm e5 c5; hmvc 0; fmvn 1;",
+        )
+        .unwrap();
+        assert_eq!(position.best_moves.len(), 2);
+        assert_eq!(position.id, None);
+    }
+
+    #[test]
+    fn bad_lines_are_errors() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -";
+        assert_eq!(
+            EpdPosition::parse("8/8/8 w -").err(),
+            Some(EpdError::FieldCount(3))
+        );
+        assert_eq!(
+            EpdPosition::parse(&format!("{} id \"x\";", start)).err(),
+            Some(EpdError::NoBestMove)
+        );
+        assert_eq!(
+            EpdPosition::parse(&format!("{} bm e5;", start)).err(),
+            Some(EpdError::BadBestMove("e5".to_string()))
+        );
+        assert!(matches!(
+            EpdPosition::parse("rnbqkbnr/pppppppp w KQkq - bm e4;"),
+            Err(EpdError::Fen(_))
+        ));
+    }
+}
@@ -1,8 +1,29 @@
+mod atomic;
+mod board;
+mod chess960;
+mod fen;
+mod notation;
+mod perft;
+mod pgn;
+mod save;
+mod setup;
+mod variant;
+mod zobrist;
+
 use colored::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
-use std::time::Instant;
 
-#[derive(Clone, Copy, PartialEq, Debug)]
+pub use board::Board;
+pub use fen::FenError;
+pub use perft::{PerftPosition, PERFT_SUITE};
+pub use pgn::PgnError;
+pub use save::SaveError;
+pub use setup::SetupError;
+pub use variant::Variant;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
 pub enum PieceType {
     Pawn,
     Knight,
@@ -12,7 +33,32 @@ pub enum PieceType {
     King,
 }
 
-#[derive(Clone, Copy, PartialEq, Debug)]
+impl PieceType {
+    // every piece type, in declaration order
+    pub fn all() -> [PieceType; 6] {
+        [
+            PieceType::Pawn,
+            PieceType::Knight,
+            PieceType::Bishop,
+            PieceType::Rook,
+            PieceType::Queen,
+            PieceType::King,
+        ]
+    }
+    // material in centipawns, the king is never traded so it's worth nothing
+    pub fn value(&self) -> i32 {
+        match self {
+            PieceType::Pawn => 100,
+            PieceType::Knight => 300,
+            PieceType::Bishop => 300,
+            PieceType::Rook => 500,
+            PieceType::Queen => 900,
+            PieceType::King => 0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
 pub enum Castling {
     BlackKingside,
     BlackQueenside,
@@ -20,7 +66,7 @@ pub enum Castling {
     WhiteQueenside,
 }
 
-#[derive(PartialEq, Clone, Copy, Debug)]
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum Color {
     Black,
     White,
@@ -35,16 +81,141 @@ impl Color {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+impl Display for Color {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Color::Black => "Black",
+            Color::White => "White",
+        })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum GameResult {
+    Ongoing,
+    // the color that delivered mate
+    Checkmate(Color),
+    Stalemate,
+    // claimed, see claim_draw
+    FiftyMoveDraw,
+    InsufficientMaterial,
+    // claimed, see claim_draw
+    ThreefoldRepetition,
+    // automatic, unlike the threefold and fifty move draws that have to be claimed
+    FivefoldRepetition,
+    SeventyFiveMoveDraw,
+    // the color that resigned
+    Resignation(Color),
+    DrawAgreed,
+    // atomic, the color whose capture blew up the other king
+    Explosion(Color),
+    // king of the hill, the color whose king made it to the center
+    KingInCenter(Color),
+    // three check, the color that gave the third check
+    ThreeCheckWin(Color),
+}
+
+impl Display for GameResult {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GameResult::Ongoing => f.write_str("Ongoing"),
+            GameResult::Checkmate(winner) => write!(f, "Checkmate, {} wins", winner),
+            GameResult::Stalemate => f.write_str("Stalemate"),
+            GameResult::FiftyMoveDraw => f.write_str("Draw by fifty-move rule"),
+            GameResult::InsufficientMaterial => f.write_str("Draw by insufficient material"),
+            GameResult::ThreefoldRepetition => f.write_str("Draw by threefold repetition"),
+            GameResult::FivefoldRepetition => f.write_str("Draw by fivefold repetition"),
+            GameResult::SeventyFiveMoveDraw => f.write_str("Draw by seventy-five-move rule"),
+            GameResult::Resignation(loser) => {
+                write!(f, "{} resigns, {} wins", loser, loser.invert())
+            }
+            GameResult::DrawAgreed => f.write_str("Draw by agreement"),
+            GameResult::Explosion(winner) => write!(f, "King exploded, {} wins", winner),
+            GameResult::KingInCenter(winner) => {
+                write!(f, "King reached the center, {} wins", winner)
+            }
+            GameResult::ThreeCheckWin(winner) => write!(f, "Third check, {} wins", winner),
+        }
+    }
+}
+
+// a draw the side to move can claim but doesn't get automatically
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DrawClaim {
+    ThreefoldRepetition,
+    FiftyMoves,
+}
+
+impl Display for DrawClaim {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DrawClaim::ThreefoldRepetition => f.write_str("threefold repetition"),
+            DrawClaim::FiftyMoves => f.write_str("fifty-move rule"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
 pub struct Piece {
     pub piece_type: PieceType,
     pub color: Color,
 }
 
+impl Piece {
+    // fen letter, uppercase for white and lowercase for black
+    pub fn to_fen_char(self) -> char {
+        let c = match self.piece_type {
+            PieceType::Pawn => 'p',
+            PieceType::Knight => 'n',
+            PieceType::Bishop => 'b',
+            PieceType::Rook => 'r',
+            PieceType::Queen => 'q',
+            PieceType::King => 'k',
+        };
+        match self.color {
+            Color::Black => c,
+            Color::White => c.to_ascii_uppercase(),
+        }
+    }
+    pub fn from_fen_char(c: char) -> Option<Piece> {
+        let piece_type = match c.to_ascii_lowercase() {
+            'p' => PieceType::Pawn,
+            'n' => PieceType::Knight,
+            'b' => PieceType::Bishop,
+            'r' => PieceType::Rook,
+            'q' => PieceType::Queen,
+            'k' => PieceType::King,
+            _ => return None,
+        };
+        let color = if c.is_ascii_uppercase() {
+            Color::White
+        } else {
+            Color::Black
+        };
+        Some(Piece { piece_type, color })
+    }
+}
+
 // (row, col)
 pub type Square = (i8, i8);
 
-#[derive(Clone, Copy, Debug)]
+// Square is a tuple alias, so conversions to board indices live on a trait
+pub trait SquareExt {
+    // board array indices, None if the square is off the board
+    fn to_indices(self) -> Option<(usize, usize)>;
+    fn from_indices(row: usize, col: usize) -> Square;
+}
+
+impl SquareExt for Square {
+    fn to_indices(self) -> Option<(usize, usize)> {
+        is_valid_square(&self).map(|(row, col)| (row as usize, col as usize))
+    }
+    fn from_indices(row: usize, col: usize) -> Square {
+        (row as i8, col as i8)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
 pub struct CastlingRights {
     pub white_queenside: bool,
     pub white_kingside: bool,
@@ -63,55 +234,108 @@ impl Default for CastlingRights {
     }
 }
 
+impl CastlingRights {
+    const NONE: CastlingRights = CastlingRights {
+        white_queenside: false,
+        white_kingside: false,
+        black_queenside: false,
+        black_kingside: false,
+    };
+    // every right that's in either
+    fn union(self, other: CastlingRights) -> CastlingRights {
+        CastlingRights {
+            white_queenside: self.white_queenside || other.white_queenside,
+            white_kingside: self.white_kingside || other.white_kingside,
+            black_queenside: self.black_queenside || other.black_queenside,
+            black_kingside: self.black_kingside || other.black_kingside,
+        }
+    }
+}
+
+// columns the king and rooks start on, the same for both colors. only chess960 moves them off
+// the e, a and h files
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub struct CastlingFiles {
+    pub king: i8,
+    pub queenside_rook: i8,
+    pub kingside_rook: i8,
+}
+
+impl Default for CastlingFiles {
+    fn default() -> Self {
+        CastlingFiles {
+            king: 4,
+            queenside_rook: 0,
+            kingside_rook: 7,
+        }
+    }
+}
+
+// everything that makes two positions the same for repetition purposes
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct PositionKey {
+    pub board: Board,
+    pub turn: Color,
+    pub castling_rights: CastlingRights,
+    pub en_passant_target_square: Option<Square>,
+}
+
+// saved games skip everything that can be worked out again from the moves, see save.rs
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Game {
-    pub board: [[Option<Piece>; 8]; 8],
+    pub board: Board,
     pub turn: Color,
     pub castling_rights: CastlingRights,
+    pub castling_files: CastlingFiles,
     pub en_passant_target_square: Option<Square>,
     pub halfmove_clock: u8,
     pub fullmove_number: u16,
+    #[serde(default)]
+    pub variant: Variant,
+    // checks each side has given, indexed by `color as usize`. see checks()
+    #[serde(default)]
+    pub checks_given: [u8; 2],
     pub moves: Vec<Move>,
+    // moves taken back with unmake_move_and_recalculate, most recent last. cleared by a new move
+    pub redo: Vec<Move>,
+    // a resignation or agreed draw, the players ended the game rather than the board
+    #[serde(default)]
+    pub declared_result: Option<GameResult>,
+    // the side with a draw offer standing, it lapses when the other side moves instead
+    #[serde(default)]
+    pub draw_offer: Option<Color>,
+    #[serde(skip)]
     pub legal_moves: [[Vec<Move>; 8]; 8],
+    // state from before the first move, restored when every move is unmade
+    pub start_halfmove_clock: u8,
+    pub start_en_passant_target_square: Option<Square>,
+    // how many times each position has occurred, including the current one
+    #[serde(skip)]
+    pub position_counts: HashMap<PositionKey, u8>,
+    // zobrist hash of the current position, kept up to date by make_move/unmake_move
+    #[serde(skip)]
+    pub zobrist: u64,
+    // moves for the pieces of both colors before king safety is checked, so request_move only
+    // has to regenerate the squares its move affected
+    #[serde(skip)]
+    pseudo_legal_moves: [[Vec<Move>; 8]; 8],
+    // false when only the side to move's pseudo legal moves were generated
+    #[serde(skip)]
+    pseudo_legal_moves_both_colors: bool,
+    // how many moves had been made at each null move still on and the en passant target from
+    // before it, most recent last
+    #[serde(skip)]
+    null_moves: Vec<(usize, Option<Square>)>,
 }
 
 impl Display for Game {
+    // `{}` prints the colored board, `{:#}` adds rank/file labels and a turn footer
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        for (row, prow) in self.board.iter().enumerate() {
-            for (col, piece) in prow.iter().enumerate() {
-                let piecestring = match piece {
-                    Some(p) => match p.color {
-                        Color::Black => match p.piece_type {
-                            PieceType::Pawn => "p",
-                            PieceType::Knight => "n",
-                            PieceType::Bishop => "b",
-                            PieceType::Rook => "r",
-                            PieceType::Queen => "q",
-                            PieceType::King => "k",
-                        },
-                        Color::White => match p.piece_type {
-                            PieceType::Pawn => "P",
-                            PieceType::Knight => "N",
-                            PieceType::Bishop => "B",
-                            PieceType::Rook => "R",
-                            PieceType::Queen => "Q",
-                            PieceType::King => "K",
-                        },
-                    },
-                    None => " ",
-                };
-                let colored = if row % 2 == col % 2 {
-                    piecestring.on_truecolor(0xb5, 0x88, 0x63).black()
-                } else {
-                    piecestring.on_truecolor(0xf0, 0xd9, 0xb5).black()
-                };
-                f.write_fmt(format_args!("{}", colored))?;
-            }
-        }
-        Ok(())
+        self.write_board(f, f.alternate(), true)
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct Move {
     pub from: Square,
     pub to: Square,
@@ -121,10 +345,40 @@ pub struct Move {
     pub promotion: Option<PieceType>,
     // if the move was en passant
     pub en_passant_capture: Option<Square>,
-    // what square did the pawn double move over
+    // what square did the pawn double move over. make_move clears it on the played move when
+    // the other side can't actually take en passant
     pub en_passant_target_square: Option<Square>,
-    // half move clock
+    // half move clock after this move, reset to 0 by pawn moves and captures
     pub halfmove_clock: u8,
+    // chess960 castles, the castling rook's square. the king's landing square alone can be a
+    // plain king move too, or where it already stands, so these are played by the king taking
+    // its own rook instead
+    #[serde(default)]
+    pub chess960_rook: Option<Square>,
+    // atomic, the pieces a capture blew up and where they stood. filled in by make_move, so
+    // it's only set on moves that were played
+    #[serde(default)]
+    pub exploded: Vec<(Square, Piece)>,
+}
+
+impl Move {
+    // the square picked to play the move, see chess960_rook
+    pub fn target(&self) -> Square {
+        self.chess960_rook.unwrap_or(self.to)
+    }
+    // coordinate notation like e2e4 or e7e8q, chess960 castles like f1h1
+    pub fn to_uci(&self) -> String {
+        let mut uci = square_to_algebraic(&self.from) + &square_to_algebraic(&self.target());
+        if let Some(p) = self.promotion {
+            uci.push(match p {
+                PieceType::Knight => 'n',
+                PieceType::Bishop => 'b',
+                PieceType::Rook => 'r',
+                _ => 'q',
+            });
+        }
+        uci
+    }
 }
 
 impl Default for Move {
@@ -144,10 +398,26 @@ impl Default for Move {
             en_passant_capture: None,
             en_passant_target_square: None,
             halfmove_clock: 0,
+            chess960_rook: None,
+            exploded: vec![],
         }
     }
 }
 
+// algebraic name like e4, row 0 is rank 8 and row 7 is rank 1
+pub fn square_to_algebraic((row, col): &Square) -> String {
+    format!("{}{}", (b'a' + *col as u8) as char, 8 - row)
+}
+pub fn algebraic_to_square(s: &str) -> Option<Square> {
+    // bytes, not chars, so anything that isn't two ascii characters falls out here or below
+    let &[file, rank] = s.as_bytes() else {
+        return None;
+    };
+    let col = file.checked_sub(b'a').filter(|col| *col < 8)?;
+    let rank = rank.checked_sub(b'1').filter(|rank| *rank < 8)?;
+    Some((7 - rank as i8, col as i8))
+}
+
 pub fn is_valid_square((row, col): &Square) -> Option<Square> {
     if (0i8..8i8).contains(row) && (0i8..8i8).contains(col) {
         Some((*row, *col))
@@ -156,13 +426,161 @@ pub fn is_valid_square((row, col): &Square) -> Option<Square> {
     }
 }
 
+// if pawns of this color increase or decrease row as they advance
+pub fn pawn_direction(color: Color) -> i8 {
+    match color {
+        Color::Black => 1,
+        Color::White => -1,
+    }
+}
+
+// i can generate these dynamically but it's almost certainly faster hardcoded
+const KNIGHT_MOVES: [(i8, i8); 8] = [
+    (-2, -1),
+    (-1, -2),
+    (-2, 1),
+    (1, -2),
+    (2, -1),
+    (-1, 2),
+    (2, 1),
+    (1, 2),
+];
+const KING_MOVES: [(i8, i8); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+const ROOK_DIRECTIONS: [(i8, i8); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+const BISHOP_DIRECTIONS: [(i8, i8); 4] = [(1, 1), (-1, 1), (1, -1), (-1, -1)];
+
+// the two diagonal squares a pawn of this color on this square attacks, None if off the board
+pub fn pawn_attacks((row, col): Square, color: Color) -> [Option<Square>; 2] {
+    let torow = row + pawn_direction(color);
+    [
+        is_valid_square(&(torow, col - 1)),
+        is_valid_square(&(torow, col + 1)),
+    ]
+}
+
+// the row the pieces of this color start on
+fn home_row(color: Color) -> i8 {
+    match color {
+        Color::Black => 0,
+        Color::White => 7,
+    }
+}
+
 impl Game {
+    // uncolored version of the board for terminals that can't show backgrounds
+    pub fn to_ascii(&self, labels: bool) -> String {
+        let mut out = String::new();
+        // writing to a string can't fail
+        self.write_board(&mut out, labels, false).unwrap();
+        out
+    }
+    fn write_board(
+        &self,
+        f: &mut impl std::fmt::Write,
+        labels: bool,
+        colored: bool,
+    ) -> std::fmt::Result {
+        for (row, prow) in self.board.iter().enumerate() {
+            if labels {
+                write!(f, "{} ", 8 - row)?;
+            }
+            for (col, piece) in prow.iter().enumerate() {
+                let piecestring = match piece {
+                    Some(p) => p.to_fen_char(),
+                    // the background shows empty squares when colored, otherwise use a dot
+                    None if colored => ' ',
+                    None => '.',
+                }
+                .to_string();
+                if colored {
+                    // same square colors as the gui, a8 is light
+                    let colored = if row % 2 == col % 2 {
+                        piecestring.as_str().on_truecolor(0xf0, 0xd9, 0xb5).black()
+                    } else {
+                        piecestring.as_str().on_truecolor(0xb5, 0x88, 0x63).black()
+                    };
+                    write!(f, "{}", colored)?;
+                } else {
+                    f.write_str(&piecestring)?;
+                }
+            }
+            f.write_char('\n')?;
+        }
+        if labels {
+            f.write_str("  abcdefgh\n")?;
+            match self.result() {
+                GameResult::Ongoing => writeln!(f, "{} to move", self.turn)?,
+                result => writeln!(f, "{}", result)?,
+            }
+        }
+        Ok(())
+    }
     pub fn piece_at_square(&self, square: &Square) -> &Option<Piece> {
-        match is_valid_square(square) {
-            Some((row, col)) => &self.board[row as usize][col as usize],
-            None => &None,
+        self.board.get(square)
+    }
+    // every board write during play goes through here so the zobrist hash stays in sync
+    fn set_piece(&mut self, square: &Square, piece: Option<Piece>) {
+        // only called with squares from generated moves, which are always on the board
+        let keys = zobrist::keys();
+        if let Some(old) = self.board.get(square) {
+            self.zobrist ^= keys.piece(old, square);
+        }
+        if let Some(new) = &piece {
+            self.zobrist ^= keys.piece(new, square);
+        }
+        self.board.set(square, piece);
+    }
+    // where the rook starts and where it ends up when castling. the king always lands on the c or
+    // g file and the rook next to it on the inside, wherever they started
+    fn castling_rook_squares(&self, castle: Castling) -> (Square, Square) {
+        let files = &self.castling_files;
+        match castle {
+            Castling::BlackKingside => ((0, files.kingside_rook), (0, 5)),
+            Castling::BlackQueenside => ((0, files.queenside_rook), (0, 3)),
+            Castling::WhiteKingside => ((7, files.kingside_rook), (7, 5)),
+            Castling::WhiteQueenside => ((7, files.queenside_rook), (7, 3)),
         }
     }
+    // the castling right that goes with the piece on the square if it's a rook still on its
+    // home square, and hasn't been lost yet. it goes once that rook moves or is captured
+    fn rook_castle_rights(&self, square: &Square) -> CastlingRights {
+        let mut losing = CastlingRights::NONE;
+        let Some(piece) = self.piece_at_square(square) else {
+            return losing;
+        };
+        let (row, col) = *square;
+        if piece.piece_type != PieceType::Rook || row != home_row(piece.color) {
+            return losing;
+        }
+        let files = &self.castling_files;
+        let rights = &self.castling_rights;
+        match piece.color {
+            Color::Black => {
+                if col == files.queenside_rook {
+                    losing.black_queenside = rights.black_queenside;
+                } else if col == files.kingside_rook {
+                    losing.black_kingside = rights.black_kingside;
+                }
+            }
+            Color::White => {
+                if col == files.queenside_rook {
+                    losing.white_queenside = rights.white_queenside;
+                } else if col == files.kingside_rook {
+                    losing.white_kingside = rights.white_kingside;
+                }
+            }
+        }
+        losing
+    }
     fn generic_move(&self, from: &Square, to: Square) -> Option<Move> {
         // return no move if invalid
         is_valid_square(&to)?;
@@ -173,7 +591,7 @@ impl Game {
             None => Some(Move {
                 from: *from,
                 to,
-                halfmove_clock: self.halfmove_clock + 1,
+                halfmove_clock: self.halfmove_clock.saturating_add(1),
                 ..Default::default()
             }),
             Some(capture_piece) => {
@@ -182,6 +600,7 @@ impl Game {
                         from: *from,
                         to,
                         capture: Some(capture_piece.piece_type),
+                        losing_castle_rights: self.rook_castle_rights(&to),
                         ..Default::default()
                     })
                 } else {
@@ -191,53 +610,98 @@ impl Game {
         }
     }
     pub fn legal_moves_on_square(&self, square: Square) -> &Vec<Move> {
-        &self.legal_moves[square.0 as usize][square.1 as usize]
+        static NO_MOVES: Vec<Move> = Vec::new();
+        match square.to_indices() {
+            Some((row, col)) => &self.legal_moves[row][col],
+            None => &NO_MOVES,
+        }
+    }
+    // same check as request_move without playing it, a promotion counts whatever it promotes to
+    pub fn is_legal(&self, from: &Square, to: &Square) -> bool {
+        self.legal_moves_on_square(*from)
+            .iter()
+            .any(|m| m.target() == *to)
+    }
+    // promotion is only checked against moves that actually promote, like request_move_promoting
+    pub fn is_legal_promoting(
+        &self,
+        from: &Square,
+        to: &Square,
+        promotion: Option<PieceType>,
+    ) -> bool {
+        self.find_legal_move(from, to, promotion).is_some()
+    }
+    fn find_legal_move(
+        &self,
+        from: &Square,
+        to: &Square,
+        promotion: Option<PieceType>,
+    ) -> Option<&Move> {
+        self.legal_moves_on_square(*from)
+            .iter()
+            .find(|m| m.target() == *to && (m.promotion.is_none() || m.promotion == promotion))
+    }
+    // every legal move for the side to move, square by square from a8
+    pub fn all_legal_moves(&self) -> impl Iterator<Item = &Move> {
+        self.legal_moves.iter().flatten().flatten()
+    }
+    // the legal moves that take a piece, en passant included
+    pub fn capture_moves(&self) -> Vec<Move> {
+        self.all_legal_moves()
+            .filter(|m| m.capture.is_some())
+            .cloned()
+            .collect()
+    }
+    pub fn legal_move_count(&self) -> usize {
+        self.legal_moves.iter().flatten().map(Vec::len).sum()
+    }
+    // stops at the first square with a legal move, cheaper than counting them all
+    pub fn has_any_legal_move(&self) -> bool {
+        self.legal_moves
+            .iter()
+            .flatten()
+            .any(|square_moves| !square_moves.is_empty())
     }
 
-    fn compute_legal_moves_on_square(&self, square: Square) -> Vec<Move> {
+    // moves for the piece on square if it belongs to color, not checked for king safety
+    fn compute_legal_moves_on_square(&self, square: Square, color: Color) -> Vec<Move> {
         let piece = self.piece_at_square(&square);
         let mut moves = vec![];
         if let Some(piece_some) = piece {
-            if piece_some.color != self.turn {
+            if piece_some.color != color {
                 return moves;
             }
             let (row, col) = square;
             match piece_some.piece_type {
                 PieceType::Pawn => {
-                    // if to increase row or decrease row
-                    let direction: i8 = match piece_some.color {
-                        Color::Black => 1,
-                        Color::White => -1,
-                    };
+                    let direction = pawn_direction(piece_some.color);
                     let mut pawn_moves: Vec<Move> = vec![];
                     let torow = row + direction;
                     // diagonal captures
-                    for capture_direction in [-1i8, 1i8] {
-                        // if the diagonal is a valid square
-                        if let Some(capture_square) =
-                            is_valid_square(&(torow, col + capture_direction))
-                        {
-                            // if there's a piece on the diagonal
-                            if let Some(capture) = self.piece_at_square(&capture_square) {
-                                // if the piece is captureable
-                                if capture.color != piece_some.color {
-                                    pawn_moves.push(Move {
-                                        from: square,
-                                        to: capture_square,
-                                        capture: Some(capture.piece_type),
-                                        ..Default::default()
-                                    });
-                                }
-                                // no piece but en passant time
-                            } else if Some(capture_square) == self.en_passant_target_square {
+                    for capture_square in
+                        pawn_attacks(square, piece_some.color).into_iter().flatten()
+                    {
+                        // if there's a piece on the diagonal
+                        if let Some(capture) = self.piece_at_square(&capture_square) {
+                            // if the piece is captureable
+                            if capture.color != piece_some.color {
                                 pawn_moves.push(Move {
                                     from: square,
                                     to: capture_square,
-                                    capture: Some(PieceType::Pawn),
-                                    en_passant_capture: Some((row, col + capture_direction)),
+                                    capture: Some(capture.piece_type),
+                                    losing_castle_rights: self.rook_castle_rights(&capture_square),
                                     ..Default::default()
                                 });
                             }
+                            // no piece but en passant time
+                        } else if Some(capture_square) == self.en_passant_target_square {
+                            pawn_moves.push(Move {
+                                from: square,
+                                to: capture_square,
+                                capture: Some(PieceType::Pawn),
+                                en_passant_capture: Some((row, capture_square.1)),
+                                ..Default::default()
+                            });
                         }
                     }
                     // if directly ahead is empty
@@ -251,9 +715,7 @@ impl Game {
                         });
                         // this can only happen if the last square was empty and pawns at initial rows
                         // pawns cant move backwards nor jump over other pieces
-                        if (row == 6 && piece_some.color == Color::White)
-                            || (row == 1 && piece_some.color == Color::Black)
-                        {
+                        if row == self.variant.pawn_start_row(piece_some.color) {
                             // always valid square
                             let two_ahead = (row + direction * 2, col);
                             if self.piece_at_square(&two_ahead).is_none() {
@@ -270,27 +732,21 @@ impl Game {
                     let promotion = torow == 7 || torow == 0;
                     for mut mov in pawn_moves {
                         if promotion {
-                            mov.promotion = Some(PieceType::Queen);
-                            moves.push(mov.clone());
-                            mov.promotion = Some(PieceType::Knight);
-                            moves.push(mov.clone());
+                            for piece_type in [
+                                PieceType::Queen,
+                                PieceType::Rook,
+                                PieceType::Bishop,
+                                PieceType::Knight,
+                            ] {
+                                mov.promotion = Some(piece_type);
+                                moves.push(mov.clone());
+                            }
                         } else {
                             moves.push(mov);
                         }
                     }
                 }
                 PieceType::Knight => {
-                    // i can generate this dynamically but it's almost certainly faster hardcoded
-                    const KNIGHT_MOVES: [(i8, i8); 8] = [
-                        (-2, -1),
-                        (-1, -2),
-                        (-2, 1),
-                        (1, -2),
-                        (2, -1),
-                        (-1, 2),
-                        (2, 1),
-                        (1, 2),
-                    ];
                     for mov in KNIGHT_MOVES {
                         if let Some(m) = self.generic_move(&square, (row + mov.0, col + mov.1)) {
                             moves.push(m);
@@ -298,20 +754,23 @@ impl Game {
                     }
                 }
                 PieceType::King => {
-                    // i can generate this dynamically but it's almost certainly faster hardcoded
-                    const KING_MOVES: [(i8, i8); 8] = [
-                        (-1, -1),
-                        (-1, 0),
-                        (-1, 1),
-                        (0, -1),
-                        (0, 1),
-                        (1, -1),
-                        (1, 0),
-                        (1, 1),
-                    ];
-
+                    // update castling rights only if needed so we can unmake move
+                    let lose_all_castling = CastlingRights {
+                        white_queenside: piece_some.color == Color::White
+                            && self.castling_rights.white_queenside,
+                        white_kingside: piece_some.color == Color::White
+                            && self.castling_rights.white_kingside,
+                        black_queenside: piece_some.color == Color::Black
+                            && self.castling_rights.black_queenside,
+                        black_kingside: piece_some.color == Color::Black
+                            && self.castling_rights.black_kingside,
+                    };
                     for mov in KING_MOVES {
-                        if let Some(m) = self.generic_move(&square, (row + mov.0, col + mov.1)) {
+                        if let Some(mut m) = self.generic_move(&square, (row + mov.0, col + mov.1))
+                        {
+                            // any king move gives up castling, on top of whatever it captured
+                            m.losing_castle_rights =
+                                m.losing_castle_rights.union(lose_all_castling);
                             moves.push(m);
                         }
                     }
@@ -324,49 +783,37 @@ impl Game {
                         Color::Black => self.castling_rights.black_kingside,
                         Color::White => self.castling_rights.white_kingside,
                     };
-                    // update castling rights only if needed so we can unmake move
-                    let lose_all_castling = CastlingRights {
-                        white_queenside: piece_some.color == Color::White
-                            && self.castling_rights.white_queenside,
-                        white_kingside: piece_some.color == Color::White
-                            && self.castling_rights.white_kingside,
-                        black_queenside: piece_some.color == Color::Black
-                            && self.castling_rights.black_queenside,
-                        black_kingside: piece_some.color == Color::Black
-                            && self.castling_rights.black_kingside,
+                    let kingside = match piece_some.color {
+                        Color::Black => Castling::BlackKingside,
+                        Color::White => Castling::WhiteKingside,
                     };
-                    if castling_kingside {
-                        // king and rook will be in valid positions if true, just check if inbetween is empty
-                        if self.piece_at_square(&(row, 5i8)).is_none()
-                            && self.piece_at_square(&(row, 6i8)).is_none()
-                        {
-                            moves.push(Move {
-                                from: square,
-                                to: (row, 6i8),
-                                castle: Some(match piece_some.color {
-                                    Color::Black => Castling::BlackKingside,
-                                    Color::White => Castling::WhiteKingside,
-                                }),
-                                losing_castle_rights: lose_all_castling,
-                                halfmove_clock: self.halfmove_clock + 1,
-                                ..Default::default()
-                            })
+                    let queenside = match piece_some.color {
+                        Color::Black => Castling::BlackQueenside,
+                        Color::White => Castling::WhiteQueenside,
+                    };
+                    for (allowed, castle, king_to) in [
+                        (castling_kingside, kingside, 6i8),
+                        (castling_queenside, queenside, 2i8),
+                    ] {
+                        if !allowed {
+                            continue;
                         }
-                    }
-                    if castling_queenside {
-                        // king and rook will be in valid positions if true, just check if inbetween is empty
-                        if self.piece_at_square(&(row, 1i8)).is_none()
-                            && self.piece_at_square(&(row, 2i8)).is_none()
-                            && self.piece_at_square(&(row, 3i8)).is_none()
-                        {
+                        // king and rook will be in valid positions if true, just check that
+                        // everything they pass over or land on is empty apart from themselves
+                        let ((_, rook_col), (_, rook_to)) = self.castling_rook_squares(castle);
+                        let cols = [col, king_to, rook_col, rook_to];
+                        let (min, max) = (*cols.iter().min().unwrap(), *cols.iter().max().unwrap());
+                        if (min..=max).all(|c| {
+                            c == col || c == rook_col || self.piece_at_square(&(row, c)).is_none()
+                        }) {
                             moves.push(Move {
                                 from: square,
-                                to: (row, 2i8),
-                                castle: Some(match piece_some.color {
-                                    Color::Black => Castling::BlackQueenside,
-                                    Color::White => Castling::WhiteQueenside,
-                                }),
+                                to: (row, king_to),
+                                castle: Some(castle),
                                 losing_castle_rights: lose_all_castling,
+                                halfmove_clock: self.halfmove_clock.saturating_add(1),
+                                chess960_rook: (self.castling_files != CastlingFiles::default())
+                                    .then_some((row, rook_col)),
                                 ..Default::default()
                             })
                         }
@@ -375,56 +822,18 @@ impl Game {
                 // queen, rook, and bishop all move similairly so theyre lumped together
                 _ => {
                     // given a direction, repeatedly move until unable (capture, own piece, edge of board)
+                    // a rook leaving its home square takes its castling right with it, so we
+                    // aren't computing this constantly
+                    let moving_rights = self.rook_castle_rights(&square);
                     let mut repeated_moves_on_direction = |dirs: [(i8, i8); 4]| {
-                        // so we aren't computing this constantly
-                        let rook = piece_some.piece_type == PieceType::Rook;
-
                         for (mrow, mcol) in dirs {
                             let mut offset = (mrow, mcol);
                             while let Some(mut m) =
                                 self.generic_move(&square, (row + offset.0, col + offset.1))
                             {
                                 let capture = m.capture.is_some();
-
-                                // handle castling rights
-                                if rook {
-                                    match piece_some.color {
-                                        Color::Black => {
-                                            if row == 0 {
-                                                match col {
-                                                    0 => {
-                                                        m.losing_castle_rights.black_queenside =
-                                                            self.castling_rights.black_queenside
-                                                    }
-
-                                                    7 => {
-                                                        m.losing_castle_rights.black_kingside =
-                                                            self.castling_rights.black_kingside
-                                                    }
-
-                                                    _ => {}
-                                                }
-                                            }
-                                        }
-                                        Color::White => {
-                                            if row == 7 && (col == 0 || col == 7) {
-                                                match col {
-                                                    0 => {
-                                                        m.losing_castle_rights.white_queenside =
-                                                            self.castling_rights.white_queenside
-                                                    }
-
-                                                    7 => {
-                                                        m.losing_castle_rights.white_kingside =
-                                                            self.castling_rights.white_kingside
-                                                    }
-                                                    _ => {}
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-
+                                m.losing_castle_rights =
+                                    m.losing_castle_rights.union(moving_rights);
                                 moves.push(m);
                                 if capture {
                                     break;
@@ -438,23 +847,13 @@ impl Game {
                     if piece_some.piece_type == PieceType::Rook
                         || piece_some.piece_type == PieceType::Queen
                     {
-                        repeated_moves_on_direction([
-                            (1i8, 0i8),
-                            (0i8, 1i8),
-                            (-1i8, 0i8),
-                            (0i8, -1i8),
-                        ]);
+                        repeated_moves_on_direction(ROOK_DIRECTIONS);
                     }
                     // diagonals
                     if piece_some.piece_type == PieceType::Bishop
                         || piece_some.piece_type == PieceType::Queen
                     {
-                        repeated_moves_on_direction([
-                            (1i8, 1i8),
-                            (-1i8, 1i8),
-                            (1i8, -1i8),
-                            (-1i8, -1i8),
-                        ]);
+                        repeated_moves_on_direction(BISHOP_DIRECTIONS);
                     }
                 }
             }
@@ -462,106 +861,653 @@ impl Game {
         moves
     }
     fn move_piece(&mut self, from: &Square, to: &Square) {
-        self.board[to.0 as usize][to.1 as usize] =
-            self.board[from.0 as usize][from.1 as usize].take();
-    }
-    fn any_king_captures(&self) -> bool {
-        for row2 in 0i8..8 {
-            for col2 in 0i8..8 {
-                for mv2 in self.legal_moves_on_square((row2, col2)) {
-                    if let Some(c) = mv2.capture {
-                        if c == PieceType::King {
+        let piece = *self.piece_at_square(from);
+        self.set_piece(from, None);
+        self.set_piece(to, piece);
+    }
+    // looks outwards from the square for pieces of that color aimed back at it, instead of
+    // generating their moves
+    pub fn is_square_attacked(&self, square: &Square, by: Color) -> bool {
+        let (row, col) = *square;
+        let is = |square: Square, piece_types: &[PieceType]| match self.piece_at_square(&square) {
+            Some(p) => p.color == by && piece_types.contains(&p.piece_type),
+            None => false,
+        };
+        // a pawn of `by` attacks this square from where a pawn of the other color would attack
+        if pawn_attacks(*square, by.invert())
+            .into_iter()
+            .flatten()
+            .any(|s| is(s, &[PieceType::Pawn]))
+        {
+            return true;
+        }
+        for (jumps, piece_type) in [
+            (KNIGHT_MOVES, PieceType::Knight),
+            (KING_MOVES, PieceType::King),
+        ] {
+            if jumps
+                .iter()
+                .filter_map(|(mrow, mcol)| is_valid_square(&(row + mrow, col + mcol)))
+                .any(|s| is(s, &[piece_type]))
+            {
+                return true;
+            }
+        }
+        for (directions, piece_type) in [
+            (ROOK_DIRECTIONS, PieceType::Rook),
+            (BISHOP_DIRECTIONS, PieceType::Bishop),
+        ] {
+            for (mrow, mcol) in directions {
+                let mut offset = (row + mrow, col + mcol);
+                // the first piece along the ray is the only one that can attack down it
+                while let Some(s) = is_valid_square(&offset) {
+                    if self.piece_at_square(&s).is_some() {
+                        if is(s, &[piece_type, PieceType::Queen]) {
                             return true;
                         }
+                        break;
                     }
+                    offset.0 += mrow;
+                    offset.1 += mcol;
                 }
             }
         }
         false
     }
+    // every square the piece on the square attacks, whoever's turn it is. pawns attack their
+    // diagonals even when they're empty, and sliders stop on the first piece of either color
+    // since they defend their own pieces too. empty for an empty square
+    pub fn attacks_from(&self, square: &Square) -> Vec<Square> {
+        let Some(piece) = self.piece_at_square(square) else {
+            return vec![];
+        };
+        let (row, col) = *square;
+        let jumps = |moves: [(i8, i8); 8]| {
+            moves
+                .iter()
+                .filter_map(|(mrow, mcol)| is_valid_square(&(row + mrow, col + mcol)))
+                .collect()
+        };
+        let slides = |directions: &[(i8, i8)]| {
+            let mut attacked = vec![];
+            for (mrow, mcol) in directions {
+                let mut offset = (row + mrow, col + mcol);
+                while let Some(s) = is_valid_square(&offset) {
+                    attacked.push(s);
+                    if self.piece_at_square(&s).is_some() {
+                        break;
+                    }
+                    offset.0 += mrow;
+                    offset.1 += mcol;
+                }
+            }
+            attacked
+        };
+        match piece.piece_type {
+            PieceType::Pawn => pawn_attacks(*square, piece.color)
+                .into_iter()
+                .flatten()
+                .collect(),
+            PieceType::Knight => jumps(KNIGHT_MOVES),
+            PieceType::King => jumps(KING_MOVES),
+            PieceType::Rook => slides(&ROOK_DIRECTIONS),
+            PieceType::Bishop => slides(&BISHOP_DIRECTIONS),
+            PieceType::Queen => slides(&[ROOK_DIRECTIONS, BISHOP_DIRECTIONS].concat()),
+        }
+    }
+    // (pinned, pinner) for each piece of color that can't leave the line between its king and
+    // an enemy slider. empty without a king
+    pub fn pinned_pieces(&self, color: Color) -> Vec<(Square, Square)> {
+        let Some((row, col)) = self.king_square(color) else {
+            return vec![];
+        };
+        let mut pins = vec![];
+        for (directions, piece_type) in [
+            (ROOK_DIRECTIONS, PieceType::Rook),
+            (BISHOP_DIRECTIONS, PieceType::Bishop),
+        ] {
+            for (mrow, mcol) in directions {
+                let mut offset = (row + mrow, col + mcol);
+                // the first of the king's own pieces along the ray, pinned if a slider is behind it
+                let mut shield = None;
+                while let Some(s) = is_valid_square(&offset) {
+                    if let Some(p) = self.piece_at_square(&s) {
+                        match shield {
+                            None if p.color == color => shield = Some(s),
+                            Some(pinned)
+                                if p.color != color
+                                    && [piece_type, PieceType::Queen].contains(&p.piece_type) =>
+                            {
+                                pins.push((pinned, s));
+                                break;
+                            }
+                            _ => break,
+                        }
+                    }
+                    offset.0 += mrow;
+                    offset.1 += mcol;
+                }
+            }
+        }
+        pins
+    }
+    pub fn king_square(&self, color: Color) -> Option<Square> {
+        self.board
+            .pieces()
+            .find(|(_, p)| p.piece_type == PieceType::King && p.color == color)
+            .map(|(square, _)| square)
+    }
+    // a side without a king is never in check
+    pub fn is_in_check(&self, color: Color) -> bool {
+        if self.variant == Variant::Atomic && self.kings_touching() {
+            return false;
+        }
+        self.king_square(color)
+            .is_some_and(|king| self.is_square_attacked(&king, color.invert()))
+    }
+    // whether playing mov puts the other side in check, by the piece that moved or one it
+    // uncovered. plays it on a copy, so it's not for anywhere hot
+    pub fn gives_check(&self, mov: &Move) -> bool {
+        let mut after = self.clone();
+        after.make_move(mov);
+        after.is_in_check(after.turn)
+    }
+    pub fn is_checkmate(&self) -> bool {
+        // almost every position has a move, so that's checked first
+        !self.has_any_legal_move() && self.is_in_check(self.turn)
+    }
+    pub fn is_stalemate(&self) -> bool {
+        !self.has_any_legal_move() && !self.is_in_check(self.turn)
+    }
+    // positions where neither side can ever mate: bare kings, a single minor piece,
+    // or only bishops that all stand on the same color square
+    pub fn insufficient_material(&self) -> bool {
+        let mut minor_pieces = 0;
+        let mut bishop_square_colors = vec![];
+        for (row, prow) in self.board.iter().enumerate() {
+            for (col, piece) in prow.iter().enumerate() {
+                match piece.map(|p| p.piece_type) {
+                    None | Some(PieceType::King) => {}
+                    Some(PieceType::Bishop) => {
+                        minor_pieces += 1;
+                        bishop_square_colors.push((row + col) % 2);
+                    }
+                    Some(PieceType::Knight) => minor_pieces += 1,
+                    // pawns can promote and rooks/queens can mate
+                    Some(_) => return false,
+                }
+            }
+        }
+        minor_pieces <= 1
+            || (bishop_square_colors.len() == minor_pieces
+                && bishop_square_colors
+                    .iter()
+                    .all(|c| *c == bishop_square_colors[0]))
+    }
+    // total piece value `color` has on the board, promotions included
+    pub fn material_count(&self, color: Color) -> i32 {
+        PieceType::all()
+            .iter()
+            .map(|piece_type| {
+                let count = self
+                    .board
+                    .pieces()
+                    .filter(|(_, p)| p.color == color && p.piece_type == *piece_type)
+                    .count();
+                count as i32 * piece_type.value()
+            })
+            .sum()
+    }
+    pub fn position_key(&self) -> PositionKey {
+        PositionKey {
+            board: self.board,
+            turn: self.turn,
+            castling_rights: self.castling_rights,
+            en_passant_target_square: self.en_passant_target_square,
+        }
+    }
+    // board, turn, castling and en passant match, clocks and history don't matter
+    pub fn same_position_as(&self, other: &Game) -> bool {
+        self.position_key() == other.position_key()
+    }
+    fn record_position(&mut self) {
+        *self.position_counts.entry(self.position_key()).or_insert(0) += 1;
+    }
+    // how many times the position on the board has come up, this time included
+    pub fn repetitions(&self) -> u8 {
+        self.position_counts
+            .get(&self.position_key())
+            .copied()
+            .unwrap_or(0)
+    }
+    // only the current position counts, a repetition earlier in the game is gone once play
+    // moves on from it
+    pub fn is_threefold_repetition(&self) -> bool {
+        self.repetitions() >= 3
+    }
+    pub fn is_fivefold_repetition(&self) -> bool {
+        self.repetitions() >= 5
+    }
+    // when more than one ending applies, the first one here wins: resignations, agreed and
+    // claimed draws and variant wins, then checkmate and stalemate, then insufficient material,
+    // fivefold repetition and finally the seventy-five move rule. so a mate on the 150th
+    // halfmove is still a mate, and a draw that holds for more than one reason reports the most
+    // permanent one. threefold repetition and the fifty move rule only end the game once
+    // claimed, see can_claim_draw
+    pub fn result(&self) -> GameResult {
+        if let Some(result) = self.declared_result.or_else(|| self.variant_result()) {
+            result
+        } else if self.is_checkmate() {
+            GameResult::Checkmate(self.turn.invert())
+        } else if self.is_stalemate() {
+            GameResult::Stalemate
+        } else if self.insufficient_material() && self.insufficient_material_draws() {
+            GameResult::InsufficientMaterial
+        } else if self.is_fivefold_repetition() {
+            GameResult::FivefoldRepetition
+        } else if self.halfmove_clock >= 150 {
+            GameResult::SeventyFiveMoveDraw
+        } else {
+            GameResult::Ongoing
+        }
+    }
+    // the draw either player could claim right now, None if there isn't one or the game is over
+    pub fn can_claim_draw(&self) -> Option<DrawClaim> {
+        if self.result() != GameResult::Ongoing {
+            None
+        } else if self.is_threefold_repetition() {
+            Some(DrawClaim::ThreefoldRepetition)
+        } else if self.halfmove_clock >= 100 {
+            Some(DrawClaim::FiftyMoves)
+        } else {
+            None
+        }
+    }
+    // ends the game in a draw if one can be claimed, undone like a resignation
+    pub fn claim_draw(&mut self) -> bool {
+        let Some(claim) = self.can_claim_draw() else {
+            return false;
+        };
+        self.declared_result = Some(match claim {
+            DrawClaim::ThreefoldRepetition => GameResult::ThreefoldRepetition,
+            DrawClaim::FiftyMoves => GameResult::FiftyMoveDraw,
+        });
+        self.draw_offer = None;
+        true
+    }
+    // back to the standard starting position with nothing played, keeping the variant
+    pub fn reset(&mut self) {
+        let variant = self.variant;
+        *self = Game::default();
+        self.set_variant(variant);
+    }
+    // does nothing once the game is over
+    pub fn resign(&mut self, color: Color) {
+        if self.result() == GameResult::Ongoing {
+            self.declared_result = Some(GameResult::Resignation(color));
+            self.draw_offer = None;
+        }
+    }
+    // either side can offer at any point, it stands until the other side accepts or moves
+    pub fn offer_draw(&mut self, color: Color) {
+        if self.result() == GameResult::Ongoing {
+            self.draw_offer = Some(color);
+        }
+    }
+    // ends the game if the other side has a draw offer standing
+    pub fn accept_draw(&mut self, color: Color) -> bool {
+        if self.draw_offer != Some(color.invert()) || self.result() != GameResult::Ongoing {
+            return false;
+        }
+        self.declared_result = Some(GameResult::DrawAgreed);
+        self.draw_offer = None;
+        true
+    }
     fn validate_move(&mut self, mov: &Move) -> bool {
+        // an atomic king can't capture, it would be caught in its own explosion
+        if self.variant == Variant::Atomic
+            && mov.capture.is_some()
+            && self.piece_at_square(&mov.from).map(|p| p.piece_type) == Some(PieceType::King)
+        {
+            return false;
+        }
+        // the king can't castle out of check or through an attacked square, where it lands is
+        // checked with the rest of the move below
+        if mov.castle.is_some() {
+            let opponent = self.turn.invert();
+            let step = (mov.to.1 - mov.from.1).signum();
+            let mut col = mov.from.1;
+            loop {
+                if self.is_square_attacked(&(mov.from.0, col), opponent) {
+                    return false;
+                }
+                col += step;
+                if col == mov.to.1 || step == 0 {
+                    break;
+                }
+            }
+        }
+        self.validate_king_safety(mov)
+    }
+    // if the side to move has a legal en passant capture onto target. strict fen only records
+    // the target then, otherwise the same position would repeat under two different keys
+    fn en_passant_capturable(&mut self, target: Square) -> bool {
+        let row = target.0 - pawn_direction(self.turn);
+        let pawn = Some(Piece {
+            piece_type: PieceType::Pawn,
+            color: self.turn,
+        });
+        [target.1 - 1, target.1 + 1].into_iter().any(|col| {
+            *self.piece_at_square(&(row, col)) == pawn
+                && self.validate_move(&Move {
+                    from: (row, col),
+                    to: target,
+                    capture: Some(PieceType::Pawn),
+                    en_passant_capture: Some((row, target.1)),
+                    ..Default::default()
+                })
+        })
+    }
+    fn validate_king_safety(&mut self, mov: &Move) -> bool {
+        let color = self.turn;
         self.make_move(mov);
-        self.compute_legal_moves(false);
-        let caps = self.any_king_captures();
+        let safe = if self.variant == Variant::Atomic {
+            // blowing up the other king wins even out of check, as long as ours survives
+            self.king_square(color).is_some()
+                && (self.king_square(color.invert()).is_none() || !self.is_in_check(color))
+        } else {
+            !self.is_in_check(color)
+        };
         self.unmake_move();
-        self.compute_legal_moves(false);
-        // if caps {
-        //     dbg!(mov);
-        // }
-        !caps
-    }
-    fn compute_legal_moves(&mut self, validate_king_moves: bool) {
-        let now = Instant::now();
+        safe
+    }
+    // timed by benches/movegen.rs
+    pub fn compute_legal_moves(&mut self, validate_king_moves: bool) {
+        // the other color's moves are only needed by update_legal_moves, skip them for the search
+        self.compute_pseudo_legal_moves(Some(self.turn));
+        self.filter_legal_moves(validate_king_moves);
+    }
+    // for both colors if color is None
+    fn compute_pseudo_legal_moves(&mut self, color: Option<Color>) {
+        for row in 0..8 {
+            for col in 0..8 {
+                let square = Square::from_indices(row, col);
+                self.pseudo_legal_moves[row][col] = match self.piece_at_square(&square) {
+                    Some(p) if color.is_none() || color == Some(p.color) => {
+                        self.compute_legal_moves_on_square(square, p.color)
+                    }
+                    _ => vec![],
+                };
+            }
+        }
+        self.pseudo_legal_moves_both_colors = color.is_none();
+    }
+    // moves for whichever color's piece is on the square
+    fn compute_pseudo_legal_moves_on_square(&self, square: Square) -> Vec<Move> {
+        match self.piece_at_square(&square) {
+            Some(p) => self.compute_legal_moves_on_square(square, p.color),
+            None => vec![],
+        }
+    }
+    // fills legal_moves from the side to move's pseudo legal moves
+    fn filter_legal_moves(&mut self, validate_king_moves: bool) {
         let mut legal_moves: [[Vec<Move>; 8]; 8] = Default::default();
-        for row in 0i8..8 {
-            for col in 0i8..8 {
-                // compute moves normally
-                let mut square_legal_moves = self.compute_legal_moves_on_square((row, col));
-
+        for (row, row_moves) in legal_moves.iter_mut().enumerate() {
+            for (col, square_moves) in row_moves.iter_mut().enumerate() {
+                match self.board[row][col] {
+                    Some(p) if p.color == self.turn => {}
+                    _ => continue,
+                }
+                let mut square_legal_moves = self.pseudo_legal_moves[row][col].clone();
                 if validate_king_moves {
-                    // let before = square_legal_moves.len();
                     square_legal_moves.retain(|m| self.validate_move(m));
-                    // println!("{} {}", before, legal_moves.len());
                 }
-
-                legal_moves[row as usize][col as usize] = square_legal_moves;
+                *square_moves = square_legal_moves;
             }
         }
         self.legal_moves = legal_moves;
+    }
+    // same result as compute_legal_moves(true) right after make_move(mov), but only regenerates
+    // the pieces whose moves mov could have changed. expects the pseudo legal moves to be from
+    // the position before mov, if they only cover one color everything is generated once
+    fn update_legal_moves(
+        &mut self,
+        mov: &Move,
+        previous_en_passant_target_square: Option<Square>,
+        previous_castling_rights: CastlingRights,
+    ) {
+        #[cfg(feature = "timing")]
+        let now = std::time::Instant::now();
+        if self.pseudo_legal_moves_both_colors {
+            self.update_pseudo_legal_moves(
+                mov,
+                previous_en_passant_target_square,
+                previous_castling_rights,
+            );
+        } else {
+            self.compute_pseudo_legal_moves(None);
+        }
+        self.filter_legal_moves(true);
 
-        if validate_king_moves {
-            let elapsed = now.elapsed();
-            println!("Move computing took {:?}", elapsed);
+        #[cfg(debug_assertions)]
+        {
+            let mut full = self.clone();
+            full.compute_pseudo_legal_moves(None);
+            full.filter_legal_moves(true);
+            assert_eq!(
+                self.legal_moves,
+                full.legal_moves,
+                "incremental legal moves differ from a full recompute after {}",
+                mov.to_uci()
+            );
+        }
+
+        #[cfg(feature = "timing")]
+        eprintln!("updating legal moves took {:?}", now.elapsed());
+    }
+    fn update_pseudo_legal_moves(
+        &mut self,
+        mov: &Move,
+        previous_en_passant_target_square: Option<Square>,
+        previous_castling_rights: CastlingRights,
+    ) {
+        // squares whose contents changed, plus the en passant squares pawns capture onto
+        let mut changed = vec![mov.from, mov.to];
+        changed.extend(mov.en_passant_capture);
+        // the generated move doesn't know what it blew up, the one that was played does
+        if let Some(played) = self.moves.last() {
+            changed.extend(played.exploded.iter().map(|(square, _)| *square));
+        }
+        changed.extend(previous_en_passant_target_square);
+        changed.extend(self.en_passant_target_square);
+        if let Some(c) = mov.castle {
+            let (rook_from, rook_to) = self.castling_rook_squares(c);
+            changed.extend([rook_from, rook_to]);
+        }
+        let castling_changed = previous_castling_rights != self.castling_rights;
+        // every untouched move still has the old halfmove clock baked in
+        let halfmove_clock = self.halfmove_clock.saturating_add(1);
+        for row in 0..8 {
+            for col in 0..8 {
+                let square = Square::from_indices(row, col);
+                if self.pseudo_legal_moves_affected(square, &changed, castling_changed) {
+                    self.pseudo_legal_moves[row][col] =
+                        self.compute_pseudo_legal_moves_on_square(square);
+                } else if self.board[row][col].map(|p| p.piece_type) != Some(PieceType::Pawn) {
+                    // pawn moves and captures reset the clock, everything else ticks it
+                    for m in self.pseudo_legal_moves[row][col].iter_mut() {
+                        if m.capture.is_none() {
+                            m.halfmove_clock = halfmove_clock;
+                        }
+                    }
+                }
+            }
         }
     }
-    fn make_move(&mut self, mov: &Move) {
+    // if the moves of the piece on square can differ now that the changed squares did
+    fn pseudo_legal_moves_affected(
+        &self,
+        square: Square,
+        changed: &[Square],
+        castling_changed: bool,
+    ) -> bool {
+        // captures of a rook on its home square carry the castling right they take away, so any
+        // piece's moves can depend on the rights. they hardly ever change, regenerate everything
+        if changed.contains(&square) || castling_changed {
+            return true;
+        }
+        // an empty square that didn't change still has no moves
+        let Some(piece) = self.piece_at_square(&square) else {
+            return false;
+        };
+        let (row, col) = square;
+        let reaches = |offsets: &[(i8, i8)]| {
+            offsets
+                .iter()
+                .any(|(mrow, mcol)| changed.contains(&(row + mrow, col + mcol)))
+        };
+        // walks each ray up to and including the first piece, past that nothing matters
+        let ray_reaches = |directions: [(i8, i8); 4]| {
+            directions.iter().any(|(mrow, mcol)| {
+                let mut offset = (row + mrow, col + mcol);
+                while let Some(s) = is_valid_square(&offset) {
+                    if changed.contains(&s) {
+                        return true;
+                    }
+                    if self.piece_at_square(&s).is_some() {
+                        return false;
+                    }
+                    offset.0 += mrow;
+                    offset.1 += mcol;
+                }
+                false
+            })
+        };
+        match piece.piece_type {
+            PieceType::Pawn => {
+                let direction = pawn_direction(piece.color);
+                reaches(&[
+                    (direction, -1),
+                    (direction, 0),
+                    (direction, 1),
+                    (direction * 2, 0),
+                ])
+            }
+            PieceType::Knight => reaches(&KNIGHT_MOVES),
+            // castling depends on the rights and everything between king and rook, only two of
+            // these so just always regenerate them
+            PieceType::King => true,
+            PieceType::Rook => ray_reaches(ROOK_DIRECTIONS),
+            PieceType::Bishop => ray_reaches(BISHOP_DIRECTIONS),
+            PieceType::Queen => ray_reaches(ROOK_DIRECTIONS) || ray_reaches(BISHOP_DIRECTIONS),
+        }
+    }
+    pub fn make_move(&mut self, mov: &Move) {
         // full move clock
         if self.turn == Color::Black {
             self.fullmove_number += 1;
         }
+        let keys = zobrist::keys();
         // half move clock
         self.halfmove_clock = mov.halfmove_clock;
-        // en passant move
+        // en passant target, only double pawn pushes carry one so castling, promotions and every
+        // other move clear it and an unused en passant capture is gone after one move
+        self.zobrist ^= keys.en_passant(&self.en_passant_target_square)
+            ^ keys.en_passant(&mov.en_passant_target_square);
         self.en_passant_target_square = mov.en_passant_target_square;
         // en passant capture
         if let Some(c) = mov.en_passant_capture {
-            self.board[c.0 as usize][c.1 as usize] = None;
-        }
-        // castling rook
-        if let Some(c) = mov.castle {
-            match c {
-                Castling::BlackKingside => self.move_piece(&(0i8, 7i8), &(0i8, 5i8)),
-                Castling::BlackQueenside => self.move_piece(&(0i8, 0i8), &(0i8, 3i8)),
-                Castling::WhiteKingside => self.move_piece(&(7i8, 7i8), &(7i8, 5i8)),
-                Castling::WhiteQueenside => self.move_piece(&(7i8, 0i8), &(7i8, 3i8)),
-            }
+            self.set_piece(&c, None);
         }
+        // castling rook, lifted off until the king has moved since in chess960 either can land
+        // where the other started
+        let castling_rook = mov.castle.map(|c| {
+            let (rook_from, rook_to) = self.castling_rook_squares(c);
+            let rook = *self.piece_at_square(&rook_from);
+            self.set_piece(&rook_from, None);
+            (rook_to, rook)
+        });
         // castling rights
+        self.zobrist ^= keys.castling(&self.castling_rights);
         self.castling_rights.black_queenside &= !mov.losing_castle_rights.black_queenside;
         self.castling_rights.black_kingside &= !mov.losing_castle_rights.black_kingside;
         self.castling_rights.white_queenside &= !mov.losing_castle_rights.white_queenside;
         self.castling_rights.white_kingside &= !mov.losing_castle_rights.white_kingside;
+        self.zobrist ^= keys.castling(&self.castling_rights);
         // promotion
         if let Some(p) = mov.promotion {
-            self.board[mov.from.0 as usize][mov.from.1 as usize]
-                .unwrap()
-                .piece_type = p;
+            if let Some(mut piece) = *self.piece_at_square(&mov.from) {
+                piece.piece_type = p;
+                self.set_piece(&mov.from, Some(piece));
+            }
         }
         // move the piece
         self.move_piece(&mov.from, &mov.to);
-        // push move
-        self.moves.push(mov.clone());
+        if let Some((rook_to, rook)) = castling_rook {
+            self.set_piece(&rook_to, rook);
+        }
+        // push move, with whatever it blew up in atomic
+        let mut played = mov.clone();
+        if self.variant == Variant::Atomic && mov.capture.is_some() {
+            self.explode(&mut played);
+        }
+        self.moves.push(played);
         // update turn
+        self.zobrist ^= keys.turn(self.turn);
         self.turn = self.turn.invert();
+        self.zobrist ^= keys.turn(self.turn);
+        if self.variant == Variant::ThreeCheck && self.is_in_check(self.turn) {
+            self.checks_given[self.turn.invert() as usize] += 1;
+        }
+        // a double push nobody can take en passant leaves no target, so it doesn't show up in
+        // the fen or the hash
+        if let Some(target) = self.en_passant_target_square {
+            if !self.en_passant_capturable(target) {
+                self.zobrist ^= keys.en_passant(&self.en_passant_target_square);
+                self.en_passant_target_square = None;
+                if let Some(played) = self.moves.last_mut() {
+                    played.en_passant_target_square = None;
+                }
+            }
+        }
+        // repetition
+        self.record_position();
     }
-    fn unmake_move(&mut self) -> bool {
+    pub fn unmake_move(&mut self) -> bool {
         let last_mov = self.moves.pop();
         if last_mov.is_none() {
             return false;
         }
+        // repetition, before the position is taken back
+        let key = self.position_key();
+        if let Some(count) = self.position_counts.get_mut(&key) {
+            *count -= 1;
+            if *count == 0 {
+                self.position_counts.remove(&key);
+            }
+        }
+        // the check this move gave, while it's still on the board
+        if self.variant == Variant::ThreeCheck && self.is_in_check(self.turn) {
+            self.checks_given[self.turn.invert() as usize] -= 1;
+        }
+        let keys = zobrist::keys();
+        self.zobrist ^= keys.turn(self.turn);
         self.turn = self.turn.invert();
+        self.zobrist ^= keys.turn(self.turn);
         let mov = last_mov.unwrap();
+        // the capturing piece comes back with the rest of the explosion before it moves back
+        self.unexplode(&mov);
+        // castling rook, off the board while the king goes back like in make_move
+        let castling_rook = mov.castle.map(|c| {
+            let (rook_from, rook_to) = self.castling_rook_squares(c);
+            let rook = *self.piece_at_square(&rook_to);
+            self.set_piece(&rook_to, None);
+            (rook_from, rook)
+        });
         self.move_piece(&mov.to, &mov.from);
+        if let Some((rook_from, rook)) = castling_rook {
+            self.set_piece(&rook_from, rook);
+        }
 
         // full move clock
         if self.turn == Color::Black {
@@ -569,67 +1515,174 @@ impl Game {
         }
         // half move clock
         self.halfmove_clock = match self.moves.last() {
-            None => 0,
+            None => self.start_halfmove_clock,
             Some(mv) => mv.halfmove_clock,
         };
 
-        // en passant move
+        // en passant target, only double pawn pushes carry one so castling, promotions and every
+        // other move clear it and an unused en passant capture is gone after one move
+        self.zobrist ^= keys.en_passant(&self.en_passant_target_square);
         let last_move = self.moves.last();
-        if let Some(lm) = last_move {
+        // a null move right before this one already cleared it
+        if self
+            .null_moves
+            .last()
+            .is_some_and(|(ply, _)| *ply == self.moves.len())
+        {
+            self.en_passant_target_square = None;
+        } else if let Some(lm) = last_move {
             self.en_passant_target_square = lm.en_passant_target_square;
         } else {
-            self.en_passant_target_square = None;
+            self.en_passant_target_square = self.start_en_passant_target_square;
         }
+        self.zobrist ^= keys.en_passant(&self.en_passant_target_square);
 
         // en passant capture
         if let Some(c) = mov.en_passant_capture {
-            self.board[c.0 as usize][c.1 as usize] = Some(Piece {
-                piece_type: PieceType::Pawn,
-                color: self.turn.invert(),
-            });
+            self.set_piece(
+                &c,
+                Some(Piece {
+                    piece_type: PieceType::Pawn,
+                    color: self.turn.invert(),
+                }),
+            );
         } else if let Some(c) = mov.capture {
-            self.board[mov.to.0 as usize][mov.to.1 as usize] = Some(Piece {
-                piece_type: c,
-                color: self.turn.invert(),
-            });
-        }
-        // castling rook
-        if let Some(c) = mov.castle {
-            match c {
-                Castling::BlackKingside => self.move_piece(&(0i8, 5i8), &(0i8, 7i8)),
-                Castling::BlackQueenside => self.move_piece(&(0i8, 3i8), &(0i8, 0i8)),
-                Castling::WhiteKingside => self.move_piece(&(7i8, 5i8), &(7i8, 7i8)),
-                Castling::WhiteQueenside => self.move_piece(&(7i8, 3i8), &(7i8, 0i8)),
-            }
+            self.set_piece(
+                &mov.to,
+                Some(Piece {
+                    piece_type: c,
+                    color: self.turn.invert(),
+                }),
+            );
         }
         // castling rights
+        self.zobrist ^= keys.castling(&self.castling_rights);
         self.castling_rights.black_queenside |= mov.losing_castle_rights.black_queenside;
         self.castling_rights.black_kingside |= mov.losing_castle_rights.black_kingside;
         self.castling_rights.white_queenside |= mov.losing_castle_rights.white_queenside;
         self.castling_rights.white_kingside |= mov.losing_castle_rights.white_kingside;
+        self.zobrist ^= keys.castling(&self.castling_rights);
         // promotion
         if mov.promotion.is_some() {
-            self.board[mov.from.0 as usize][mov.from.1 as usize]
-                .unwrap()
-                .piece_type = PieceType::Pawn;
+            if let Some(mut piece) = *self.piece_at_square(&mov.from) {
+                piece.piece_type = PieceType::Pawn;
+                self.set_piece(&mov.from, Some(piece));
+            }
         }
         true
     }
+    // passes the turn without moving anything, for null move pruning. only the turn and en
+    // passant target change, not the clocks, history or repetition counts. like make_move the
+    // legal moves have to be recomputed afterwards
+    pub fn make_null_move(&mut self) {
+        let keys = zobrist::keys();
+        self.null_moves
+            .push((self.moves.len(), self.en_passant_target_square));
+        self.zobrist ^= keys.en_passant(&self.en_passant_target_square);
+        self.en_passant_target_square = None;
+        self.zobrist ^= keys.turn(self.turn);
+        self.turn = self.turn.invert();
+        self.zobrist ^= keys.turn(self.turn);
+        // the other color's pseudo legal moves still think it's the old turn
+        self.pseudo_legal_moves_both_colors = false;
+    }
+    // false unless the last thing played was a null move
+    pub fn unmake_null_move(&mut self) -> bool {
+        let en_passant_target_square = match self.null_moves.last() {
+            Some((ply, square)) if *ply == self.moves.len() => *square,
+            _ => return false,
+        };
+        self.null_moves.pop();
+        let keys = zobrist::keys();
+        self.zobrist ^= keys.turn(self.turn);
+        self.turn = self.turn.invert();
+        self.zobrist ^= keys.turn(self.turn);
+        self.en_passant_target_square = en_passant_target_square;
+        self.zobrist ^= keys.en_passant(&self.en_passant_target_square);
+        self.pseudo_legal_moves_both_colors = false;
+        true
+    }
+    // a resignation or agreed draw is taken back first, on its own
     pub fn unmake_move_and_recalculate(&mut self) {
-        self.unmake_move();
+        self.draw_offer = None;
+        if self.declared_result.take().is_some() {
+            return;
+        }
+        let last_mov = self.moves.last().cloned();
+        if self.unmake_move() {
+            self.redo.extend(last_mov);
+        }
         // recompute legal moves
         self.compute_legal_moves(true);
     }
-    pub fn request_move(&mut self, from: &Square, to: &Square) -> bool {
-        // clone here because I can't borrow self in self.legal_moves_on_square and self.make_move
-        for mov in self.legal_moves_on_square(*from).clone() {
-            if mov.to == *to {
-                self.make_move(&mov);
-                self.compute_legal_moves(true);
-                return true;
+    // undoes or redoes until `ply` moves have been played, stopping early if redo runs out
+    pub fn go_to_ply(&mut self, ply: usize) {
+        if ply < self.moves.len() {
+            self.declared_result = None;
+            self.draw_offer = None;
+            // legal moves only matter in the position it ends up in
+            while self.moves.len() > ply {
+                let last_mov = self.moves.last().cloned();
+                self.unmake_move();
+                self.redo.extend(last_mov);
             }
+            self.compute_legal_moves(true);
         }
-        false
+        while self.moves.len() < ply && self.redo_move_and_recalculate() {}
+    }
+    // plays the last undone move again, false if there's nothing to redo
+    pub fn redo_move_and_recalculate(&mut self) -> bool {
+        if self.declared_result.is_some() {
+            return false;
+        }
+        let Some(mov) = self.redo.pop() else {
+            return false;
+        };
+        // the move was generated in this exact position, so it can be replayed as is
+        let previous_en_passant_target_square = self.en_passant_target_square;
+        let previous_castling_rights = self.castling_rights;
+        self.make_move(&mov);
+        self.update_legal_moves(
+            &mov,
+            previous_en_passant_target_square,
+            previous_castling_rights,
+        );
+        true
+    }
+    // promotes to a queen if the move is a promotion
+    pub fn request_move(&mut self, from: &Square, to: &Square) -> bool {
+        self.request_move_promoting(from, to, Some(PieceType::Queen))
+    }
+    // promotion is only checked against moves that actually promote
+    pub fn request_move_promoting(
+        &mut self,
+        from: &Square,
+        to: &Square,
+        promotion: Option<PieceType>,
+    ) -> bool {
+        // resigned, drawn and variant won games are over even with legal moves left
+        if self.result() != GameResult::Ongoing {
+            return false;
+        }
+        // clone here because I can't borrow self in self.find_legal_move and self.make_move
+        let Some(mov) = self.find_legal_move(from, to, promotion).cloned() else {
+            return false;
+        };
+        let previous_en_passant_target_square = self.en_passant_target_square;
+        let previous_castling_rights = self.castling_rights;
+        self.make_move(&mov);
+        self.update_legal_moves(
+            &mov,
+            previous_en_passant_target_square,
+            previous_castling_rights,
+        );
+        // a new line, the undone moves don't follow from it anymore
+        self.redo.clear();
+        // moving declines the other side's draw offer
+        if self.draw_offer == Some(self.turn) {
+            self.draw_offer = None;
+        }
+        true
     }
 }
 
@@ -644,42 +1697,858 @@ const INITIAL_ROW: [PieceType; 8] = [
     PieceType::Rook,
 ];
 
-impl Default for Game {
-    fn default() -> Self {
-        const INIT_PIECE: Option<Piece> = None;
+impl Game {
+    // a game starting from this position with no moves played yet
+    fn from_position(
+        board: Board,
+        turn: Color,
+        castling_rights: CastlingRights,
+        castling_files: CastlingFiles,
+        en_passant_target_square: Option<Square>,
+        halfmove_clock: u8,
+        fullmove_number: u16,
+    ) -> Game {
         let mut game = Game {
-            board: [[INIT_PIECE; 8]; 8], // empty board
-            turn: Color::White,
-            castling_rights: Default::default(), // all true
-            en_passant_target_square: None,
-            halfmove_clock: 0,
-            fullmove_number: 0,
-            moves: Default::default(),       // empty vec
+            board,
+            turn,
+            castling_rights,
+            castling_files,
+            en_passant_target_square,
+            halfmove_clock,
+            fullmove_number,
+            variant: Variant::Standard,
+            checks_given: [0; 2],
+            moves: Default::default(), // empty vec
+            redo: Default::default(),  // empty vec
+            declared_result: None,
+            draw_offer: None,
             legal_moves: Default::default(), // empty vec
+            start_halfmove_clock: halfmove_clock,
+            start_en_passant_target_square: en_passant_target_square,
+            position_counts: Default::default(), // empty map
+            zobrist: 0,
+            pseudo_legal_moves: Default::default(), // empty vec
+            pseudo_legal_moves_both_colors: false,
+            null_moves: Default::default(), // empty vec
         };
-        // initialize top and bottom rows with the starting arrangement
-        for (index, piecetype) in INITIAL_ROW.iter().enumerate() {
-            game.board[0][index] = Some(Piece {
-                piece_type: *piecetype,
-                color: Color::Black,
-            });
-            game.board[7][index] = Some(Piece {
-                piece_type: *piecetype,
-                color: Color::White,
-            });
-        }
-        // initialize pawns
-        for i in 0..8 {
-            game.board[1][i] = Some(Piece {
-                piece_type: PieceType::Pawn,
-                color: Color::Black,
-            });
-            game.board[6][i] = Some(Piece {
-                piece_type: PieceType::Pawn,
-                color: Color::White,
-            });
+        game.drop_unusable_castling_rights();
+        if let Some(target) = game.en_passant_target_square {
+            if !game.en_passant_capturable(target) {
+                game.en_passant_target_square = None;
+                game.start_en_passant_target_square = None;
+            }
         }
+        game.zobrist = game.compute_hash();
+        game.record_position();
         game.compute_legal_moves(true);
         game
     }
+    // castling generation assumes the king and rook are on their home squares, so ignore
+    // rights the position can't back up instead of generating broken castles
+    fn drop_unusable_castling_rights(&mut self) {
+        fn is(game: &Game, square: Square, piece_type: PieceType, color: Color) -> bool {
+            match game.piece_at_square(&square) {
+                Some(p) => p.piece_type == piece_type && p.color == color,
+                None => false,
+            }
+        }
+        let files = self.castling_files;
+        let white_king = is(self, (7, files.king), PieceType::King, Color::White);
+        let black_king = is(self, (0, files.king), PieceType::King, Color::Black);
+        self.castling_rights.white_kingside &= white_king
+            && is(
+                self,
+                (7, files.kingside_rook),
+                PieceType::Rook,
+                Color::White,
+            );
+        self.castling_rights.white_queenside &= white_king
+            && is(
+                self,
+                (7, files.queenside_rook),
+                PieceType::Rook,
+                Color::White,
+            );
+        self.castling_rights.black_kingside &= black_king
+            && is(
+                self,
+                (0, files.kingside_rook),
+                PieceType::Rook,
+                Color::Black,
+            );
+        self.castling_rights.black_queenside &= black_king
+            && is(
+                self,
+                (0, files.queenside_rook),
+                PieceType::Rook,
+                Color::Black,
+            );
+    }
+}
+
+// the standard back row for both colors, with pawns in front of it
+fn starting_board(back_row: [PieceType; 8]) -> Board {
+    let mut board = Board::default();
+    for (index, piece_type) in back_row.into_iter().enumerate() {
+        board[0][index] = Some(Piece {
+            piece_type,
+            color: Color::Black,
+        });
+        board[7][index] = Some(Piece {
+            piece_type,
+            color: Color::White,
+        });
+        board[1][index] = Some(Piece {
+            piece_type: PieceType::Pawn,
+            color: Color::Black,
+        });
+        board[6][index] = Some(Piece {
+            piece_type: PieceType::Pawn,
+            color: Color::White,
+        });
+    }
+    board
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Game::from_position(
+            starting_board(INITIAL_ROW),
+            Color::White,
+            Default::default(), // all true
+            Default::default(), // e, a and h files
+            None,
+            0,
+            1, // fen move numbers start at 1
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sq(name: &str) -> Square {
+        algebraic_to_square(name).unwrap()
+    }
+
+    #[test]
+    fn algebraic_round_trips_every_square() {
+        for row in 0..8 {
+            for col in 0..8 {
+                let name = square_to_algebraic(&(row, col));
+                assert_eq!(algebraic_to_square(&name), Some((row, col)));
+            }
+        }
+        assert_eq!(square_to_algebraic(&(0, 0)), "a8");
+        assert_eq!(square_to_algebraic(&(7, 7)), "h1");
+        assert_eq!(algebraic_to_square("e4"), Some((4, 4)));
+    }
+
+    #[test]
+    fn algebraic_rejects_junk() {
+        for s in [
+            "", "e", "e44", "i1", "a0", "a9", "A1", "1a", "\u{e9}", "e\u{e9}", "\u{e9}4",
+        ] {
+            assert_eq!(algebraic_to_square(s), None, "{:?}", s);
+        }
+    }
+
+    // white and black knights out and back, every four plies repeat the start position
+    const KNIGHT_SHUFFLE: [(Square, Square); 4] = [
+        ((7, 6), (5, 5)),
+        ((0, 6), (2, 5)),
+        ((5, 5), (7, 6)),
+        ((2, 5), (0, 6)),
+    ];
+
+    fn play(game: &mut Game, moves: &[(Square, Square)]) {
+        for (from, to) in moves {
+            assert!(
+                game.request_move(from, to),
+                "{} isn't legal",
+                square_to_algebraic(from) + &square_to_algebraic(to)
+            );
+        }
+    }
+
+    fn play_uci(game: &mut Game, moves: &[&str]) {
+        for text in moves {
+            let mov = game
+                .parse_uci(text)
+                .unwrap_or_else(|| panic!("{} isn't legal", text));
+            assert!(game.request_move_promoting(&mov.from, &mov.target(), mov.promotion));
+        }
+    }
+
+    #[test]
+    fn threefold_is_claimed_not_automatic() {
+        let mut game = Game::default();
+        play(&mut game, &KNIGHT_SHUFFLE);
+        play(&mut game, &KNIGHT_SHUFFLE);
+        assert_eq!(game.repetitions(), 3);
+        assert_eq!(game.result(), GameResult::Ongoing);
+        assert_eq!(game.can_claim_draw(), Some(DrawClaim::ThreefoldRepetition));
+        let mut claimed = game.clone();
+        assert!(claimed.claim_draw());
+        assert_eq!(claimed.result(), GameResult::ThreefoldRepetition);
+        assert!(!claimed.request_move(&(6, 4), &(4, 4)));
+        // taken back like a resignation
+        claimed.unmake_move_and_recalculate();
+        assert_eq!(claimed.result(), GameResult::Ongoing);
+        // playing on instead gives the chance up
+        play(&mut game, &[((6, 4), (4, 4))]);
+        assert_eq!(game.can_claim_draw(), None);
+        assert!(!game.claim_draw());
+        assert_eq!(game.result(), GameResult::Ongoing);
+    }
+
+    #[test]
+    fn fivefold_is_automatic() {
+        let mut game = Game::default();
+        for _ in 0..3 {
+            play(&mut game, &KNIGHT_SHUFFLE);
+        }
+        assert_eq!(game.result(), GameResult::Ongoing);
+        play(&mut game, &KNIGHT_SHUFFLE);
+        assert_eq!(game.repetitions(), 5);
+        assert_eq!(game.result(), GameResult::FivefoldRepetition);
+        // over, so nothing left to claim or play
+        assert_eq!(game.can_claim_draw(), None);
+        assert!(!game.request_move(&(7, 6), &(5, 5)));
+        assert_eq!(game.moves.len(), 16);
+    }
+
+    #[test]
+    fn fifty_moves_is_claimed_seventy_five_automatic() {
+        let fen = |halfmoves: u8| format!("4k3/8/8/8/8/8/8/4K2R w - - {} 80", halfmoves);
+        let game = Game::from_fen(&fen(99)).unwrap();
+        assert_eq!(game.can_claim_draw(), None);
+        let mut game = Game::from_fen(&fen(100)).unwrap();
+        assert_eq!(game.result(), GameResult::Ongoing);
+        assert_eq!(game.can_claim_draw(), Some(DrawClaim::FiftyMoves));
+        assert!(game.claim_draw());
+        assert_eq!(game.result(), GameResult::FiftyMoveDraw);
+        let mut game = Game::from_fen(&fen(149)).unwrap();
+        play(&mut game, &[((7, 7), (6, 7))]);
+        assert_eq!(game.result(), GameResult::SeventyFiveMoveDraw);
+        assert!(!game.request_move(&(0, 4), &(0, 3)));
+    }
+
+    #[test]
+    fn square_attacks_match_the_attackers() {
+        for fen in PERFT_SUITE.iter().map(|p| p.fen) {
+            let game = Game::from_fen(fen).unwrap();
+            for by in [Color::White, Color::Black] {
+                // every square some piece of that color hits, the slow way
+                let attacked: Vec<Square> = game
+                    .board
+                    .pieces()
+                    .filter(|(_, p)| p.color == by)
+                    .flat_map(|(square, _)| game.attacks_from(&square))
+                    .collect();
+                for row in 0..8 {
+                    for col in 0..8 {
+                        assert_eq!(
+                            game.is_square_attacked(&(row, col), by),
+                            attacked.contains(&(row, col)),
+                            "{} attacked by {:?} in {}",
+                            square_to_algebraic(&(row, col)),
+                            by,
+                            fen
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    // what undo and redo have to bring back exactly
+    fn snapshot(game: &Game) -> (String, u64, Vec<String>) {
+        let mut moves: Vec<String> = game.all_legal_moves().map(Move::to_uci).collect();
+        moves.sort();
+        (game.to_fen(), game.hash(), moves)
+    }
+
+    #[test]
+    fn redo_special_moves() {
+        for (fen, uci) in [
+            // en passant, both ways
+            ("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6"),
+            ("4k3/8/8/8/3Pp3/8/8/4K3 b - d3 0 1", "e4d3"),
+            // castling on each side for each color
+            ("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "e1g1"),
+            ("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "e1c1"),
+            ("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1", "e8g8"),
+            ("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1", "e8c8"),
+            // promotions, an underpromotion and one that takes a rook and its castling right
+            ("4k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7a8q"),
+            ("4k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7a8n"),
+            ("r3k3/1P6/8/8/8/8/8/4K3 w q - 0 1", "b7a8q"),
+        ] {
+            let mut game = Game::from_fen(fen).unwrap();
+            let before = snapshot(&game);
+            play_uci(&mut game, &[uci]);
+            let after = snapshot(&game);
+            game.unmake_move_and_recalculate();
+            assert_eq!(snapshot(&game), before, "{} undone", uci);
+            assert!(game.redo_move_and_recalculate());
+            assert_eq!(snapshot(&game), after, "{} redone", uci);
+            assert!(game.redo.is_empty());
+            assert!(!game.redo_move_and_recalculate());
+            // and once more, redo leaves it undoable
+            game.unmake_move_and_recalculate();
+            assert_eq!(snapshot(&game), before, "{} undone again", uci);
+        }
+        // a fresh move throws the undone ones away
+        let mut game = Game::default();
+        play_uci(&mut game, &["e2e4"]);
+        game.unmake_move_and_recalculate();
+        play_uci(&mut game, &["d2d4"]);
+        assert!(game.redo.is_empty());
+        assert!(!game.redo_move_and_recalculate());
+    }
+
+    #[test]
+    fn en_passant_only_lasts_one_move() {
+        // white can castle, promote or push instead of taking
+        let fen = "4k3/P2p4/8/4P3/8/8/7P/4K2R b K - 0 1";
+        for instead in ["h2h3", "e1g1", "a7a8q"] {
+            let mut game = Game::from_fen(fen).unwrap();
+            play_uci(&mut game, &["d7d5"]);
+            assert!(game.parse_uci("e5d6").is_some());
+            play_uci(&mut game, &[instead]);
+            assert_eq!(game.en_passant_target_square, None, "after {}", instead);
+            play_uci(&mut game, &["e8e7"]);
+            assert!(game.parse_uci("e5d6").is_none(), "after {}", instead);
+        }
+    }
+
+    #[test]
+    fn en_passant_target_needs_a_capture() {
+        for (fen, after) in [
+            (
+                "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1",
+                "4k3/8/8/8/4P3/8/8/4K3 b - - 0 1",
+            ),
+            (
+                "4k3/8/8/8/3p4/8/4P3/4K3 w - - 0 1",
+                "4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1",
+            ),
+            // taking would leave both pawns off the rank and the king open to the rook
+            (
+                "8/8/8/8/k2p3R/8/4P3/4K3 w - - 0 1",
+                "8/8/8/8/k2pP2R/8/8/4K3 b - - 0 1",
+            ),
+        ] {
+            let mut game = Game::from_fen(fen).unwrap();
+            play_uci(&mut game, &["e2e4"]);
+            assert_eq!(game.to_fen(), after);
+            // same position however it was reached, so it has to hash the same for repetitions
+            assert_eq!(game.hash(), game.compute_hash());
+            assert_eq!(game.hash(), Game::from_fen(after).unwrap().hash());
+        }
+        // a fen naming a target nobody can take on gets it dropped
+        let game = Game::from_fen("4k3/8/8/8/4P3/8/8/4K3 b - e3 0 1").unwrap();
+        assert_eq!(game.en_passant_target_square, None);
+        assert_eq!(game.to_fen(), "4k3/8/8/8/4P3/8/8/4K3 b - - 0 1");
+    }
+
+    // two endings at once, result() reports the first in its precedence list. threefold and
+    // fifty moves come last in it by not being there at all: they're claims, so anything
+    // automatic wins over them, and between the two can_claim_draw offers threefold first
+    #[test]
+    fn result_precedence() {
+        // mate and stalemate on the 150th halfmove still count
+        let mut game = Game::from_fen("7k/8/6K1/8/8/8/8/R7 w - - 149 100").unwrap();
+        play_uci(&mut game, &["a1a8"]);
+        assert_eq!(game.halfmove_clock, 150);
+        assert_eq!(game.result(), GameResult::Checkmate(Color::White));
+        let mut game = Game::from_fen("k7/8/8/8/8/8/8/2Q1K3 w - - 149 100").unwrap();
+        play_uci(&mut game, &["c1c7"]);
+        assert_eq!(game.result(), GameResult::Stalemate);
+        // insufficient material over both the move rules
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/4KB2 w - - 150 100").unwrap();
+        assert_eq!(game.result(), GameResult::InsufficientMaterial);
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/4KB2 w - - 100 100").unwrap();
+        assert_eq!(game.can_claim_draw(), None);
+        // the fifth repetition landing on the 150th halfmove
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 134 60";
+        let mut game = Game::from_fen(start).unwrap();
+        for _ in 0..4 {
+            play(&mut game, &KNIGHT_SHUFFLE);
+        }
+        assert_eq!(game.halfmove_clock, 150);
+        assert_eq!(game.result(), GameResult::FivefoldRepetition);
+        // the third repetition landing on the 100th
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 92 60";
+        let mut game = Game::from_fen(start).unwrap();
+        play(&mut game, &KNIGHT_SHUFFLE);
+        play(&mut game, &KNIGHT_SHUFFLE);
+        assert_eq!(game.halfmove_clock, 100);
+        assert_eq!(game.result(), GameResult::Ongoing);
+        assert_eq!(game.can_claim_draw(), Some(DrawClaim::ThreefoldRepetition));
+    }
+
+    // the text the game over banner shows for each way a game can be drawn
+    #[test]
+    fn draw_result_text() {
+        let text = |game: &Game| game.result().to_string();
+        // fifty moves, claimed on the 100th halfmove
+        let mut game = Game::from_fen("8/8/5k2/8/3R4/8/K7/8 w - - 99 80").unwrap();
+        play_uci(&mut game, &["a2a3"]);
+        assert!(game.claim_draw());
+        assert_eq!(text(&game), "Draw by fifty-move rule");
+        // threefold, claimed
+        let mut game = Game::default();
+        play(&mut game, &KNIGHT_SHUFFLE);
+        play(&mut game, &KNIGHT_SHUFFLE);
+        assert!(game.claim_draw());
+        assert_eq!(text(&game), "Draw by threefold repetition");
+        // fivefold and seventy-five moves end it without a claim
+        let mut game = Game::default();
+        for _ in 0..4 {
+            play(&mut game, &KNIGHT_SHUFFLE);
+        }
+        assert_eq!(text(&game), "Draw by fivefold repetition");
+        let mut game = Game::from_fen("8/8/5k2/8/3R4/8/K7/8 w - - 149 80").unwrap();
+        play_uci(&mut game, &["a2a3"]);
+        assert_eq!(text(&game), "Draw by seventy-five-move rule");
+        // taking the last piece leaves two bare kings
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/3r4/4K3 w - - 0 1").unwrap();
+        play_uci(&mut game, &["e1d2"]);
+        assert_eq!(text(&game), "Draw by insufficient material");
+        let mut game = Game::from_fen("k7/8/8/8/8/8/8/2Q1K3 w - - 0 1").unwrap();
+        play_uci(&mut game, &["c1c7"]);
+        assert_eq!(text(&game), "Stalemate");
+        let mut game = Game::default();
+        game.offer_draw(Color::White);
+        assert!(game.accept_draw(Color::Black));
+        assert_eq!(text(&game), "Draw by agreement");
+    }
+
+    #[test]
+    fn pawn_attacks_at_the_edges() {
+        assert_eq!(
+            pawn_attacks(sq("e4"), Color::White),
+            [Some(sq("d5")), Some(sq("f5"))]
+        );
+        assert_eq!(
+            pawn_attacks(sq("e5"), Color::Black),
+            [Some(sq("d4")), Some(sq("f4"))]
+        );
+        assert_eq!(pawn_attacks(sq("a2"), Color::White), [None, Some(sq("b3"))]);
+        assert_eq!(pawn_attacks(sq("h2"), Color::White), [Some(sq("g3")), None]);
+        assert_eq!(pawn_attacks(sq("a7"), Color::Black), [None, Some(sq("b6"))]);
+        assert_eq!(pawn_attacks(sq("h7"), Color::Black), [Some(sq("g6")), None]);
+        // nothing past the last rank
+        assert_eq!(pawn_attacks(sq("a8"), Color::White), [None, None]);
+        assert_eq!(pawn_attacks(sq("h1"), Color::Black), [None, None]);
+    }
+
+    // the castles the king on square can play
+    fn castles(game: &Game, king: &str) -> Vec<Castling> {
+        game.legal_moves_on_square(sq(king))
+            .iter()
+            .filter_map(|m| m.castle)
+            .collect()
+    }
+
+    #[test]
+    fn no_castling_out_of_or_through_check() {
+        use Castling::*;
+        let white = |black_pieces: &str| {
+            Game::from_fen(&format!("{}/8/8/8/8/8/8/R3K2R w KQ - 0 1", black_pieces)).unwrap()
+        };
+        assert_eq!(
+            castles(&white("4k3"), "e1"),
+            [WhiteKingside, WhiteQueenside]
+        );
+        // a rook on f1 or g1's file
+        assert_eq!(castles(&white("4kr2"), "e1"), [WhiteQueenside]);
+        assert_eq!(castles(&white("4k1r1"), "e1"), [WhiteQueenside]);
+        assert_eq!(castles(&white("3rk3"), "e1"), [WhiteKingside]);
+        assert_eq!(castles(&white("2r1k3"), "e1"), [WhiteKingside]);
+        // b1 is crossed by the rook, not the king
+        assert_eq!(
+            castles(&white("1r2k3"), "e1"),
+            [WhiteKingside, WhiteQueenside]
+        );
+        // already in check
+        let game = Game::from_fen("4k3/8/8/8/1b6/8/8/R3K2R w KQ - 0 1").unwrap();
+        assert!(game.is_in_check(Color::White));
+        assert_eq!(castles(&game, "e1"), []);
+        // and black's side
+        let game = Game::from_fen("r3k2r/8/8/8/8/8/8/4KR2 b kq - 0 1").unwrap();
+        assert_eq!(castles(&game, "e8"), [BlackQueenside]);
+    }
+
+    #[test]
+    fn fifty_moves_of_shuffling_can_be_claimed() {
+        // the kings walk loops of 7 and 8 squares, so nothing repeats for 56 moves
+        let white = ["a2", "a3", "b3", "c3", "c2", "c1", "b1"];
+        let black = ["f6", "f7", "f8", "g8", "h8", "h7", "h6", "g6"];
+        let mut game = Game::from_fen("8/8/5k2/8/3R4/8/K7/8 w - - 0 1").unwrap();
+        for i in 0..50 {
+            assert_eq!(game.can_claim_draw(), None);
+            let step = |ring: &[&str]| (sq(ring[i % ring.len()]), sq(ring[(i + 1) % ring.len()]));
+            play(&mut game, &[step(&white), step(&black)]);
+            assert_eq!(game.halfmove_clock as usize, 2 * i + 2);
+        }
+        assert_eq!(game.repetitions(), 1);
+        assert_eq!(game.result(), GameResult::Ongoing);
+        assert_eq!(game.can_claim_draw(), Some(DrawClaim::FiftyMoves));
+        assert!(game.claim_draw());
+        assert_eq!(game.result(), GameResult::FiftyMoveDraw);
+    }
+
+    #[test]
+    fn insufficient_material_cases() {
+        for (fen, insufficient) in [
+            ("4k3/8/8/8/8/8/8/4K3 w - - 0 1", true),
+            ("4k3/8/8/8/8/8/8/4KB2 w - - 0 1", true),
+            ("4k3/8/8/8/8/8/8/4KN2 w - - 0 1", true),
+            ("4kn2/8/8/8/8/8/8/4K3 w - - 0 1", true),
+            // bishops on the same color, c8 and f1 are both light
+            ("2b1k3/8/8/8/8/8/8/4KB2 w - - 0 1", true),
+            // and on opposite colors, c1 is dark
+            ("2b1k3/8/8/8/8/8/8/2B1K3 w - - 0 1", false),
+            // two knights can't force it, but a mate is still possible
+            ("4k3/8/8/8/8/8/8/3NKN2 w - - 0 1", false),
+            ("4k3/8/8/8/8/8/8/4KN1n w - - 0 1", false),
+            ("4k3/8/8/8/8/8/P7/4K3 w - - 0 1", false),
+            ("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", false),
+        ] {
+            let game = Game::from_fen(fen).unwrap();
+            assert_eq!(game.insufficient_material(), insufficient, "{}", fen);
+            let expected = if insufficient {
+                GameResult::InsufficientMaterial
+            } else {
+                GameResult::Ongoing
+            };
+            assert_eq!(game.result(), expected, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn threefold_by_knight_shuffle() {
+        let mut game = Game::default();
+        play(&mut game, &KNIGHT_SHUFFLE);
+        assert_eq!(game.repetitions(), 2);
+        assert!(!game.is_threefold_repetition());
+        play(&mut game, &KNIGHT_SHUFFLE);
+        assert!(game.is_threefold_repetition());
+        // taking a move back takes its count back
+        game.unmake_move_and_recalculate();
+        assert!(!game.is_threefold_repetition());
+        play(&mut game, &KNIGHT_SHUFFLE[3..]);
+        assert!(game.is_threefold_repetition());
+        // it doesn't have to be the same moves each time
+        let mut game = Game::default();
+        play_uci(&mut game, &["g1f3", "g8f6", "b1c3", "b8c6"]);
+        play_uci(&mut game, &["c3b1", "c6b8", "b1c3", "b8c6"]);
+        assert_eq!(game.repetitions(), 2);
+        play_uci(&mut game, &["f3g1", "f6g8", "g1f3", "g8f6"]);
+        assert!(game.is_threefold_repetition());
+    }
+
+    #[test]
+    fn piece_values() {
+        let values: Vec<i32> = PieceType::all().iter().map(PieceType::value).collect();
+        assert_eq!(values, [100, 300, 300, 500, 900, 0]);
+    }
+
+    #[test]
+    fn fen_chars_round_trip() {
+        let mut chars = String::new();
+        for color in [Color::White, Color::Black] {
+            for piece_type in PieceType::all() {
+                let piece = Piece { piece_type, color };
+                let c = piece.to_fen_char();
+                assert_eq!(Piece::from_fen_char(c), Some(piece));
+                chars.push(c);
+            }
+        }
+        assert_eq!(chars, "PNBRQKpnbrqk");
+        for c in ['x', '1', '/', ' ', '\u{e9}'] {
+            assert_eq!(Piece::from_fen_char(c), None, "{:?}", c);
+        }
+    }
+
+    #[test]
+    fn fullmove_number_counts_from_one() {
+        let mut game = Game::default();
+        assert_eq!(game.fullmove_number, 1);
+        play_uci(&mut game, &["e2e4"]);
+        assert_eq!(game.fullmove_number, 1);
+        play_uci(&mut game, &["e7e5"]);
+        assert_eq!(game.fullmove_number, 2);
+        assert!(game.to_fen().ends_with(" 0 2"));
+        game.unmake_move_and_recalculate();
+        assert_eq!(game.fullmove_number, 1);
+        game.unmake_move_and_recalculate();
+        assert_eq!(game.to_fen(), Game::default().to_fen());
+    }
+
+    #[test]
+    fn legal_move_counts() {
+        let game = Game::default();
+        assert_eq!(game.legal_move_count(), 20);
+        assert!(game.has_any_legal_move());
+        assert!(!game.is_checkmate() && !game.is_stalemate());
+        // fool's mate
+        let mut game = Game::default();
+        play_uci(&mut game, &["f2f3", "e7e5", "g2g4", "d8h4"]);
+        assert_eq!(game.legal_move_count(), 0);
+        assert!(!game.has_any_legal_move());
+        assert!(game.is_checkmate());
+        // back rank mate, the king's own pawns box it in
+        let mut game = Game::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        assert!(!game.is_checkmate());
+        play_uci(&mut game, &["a1a8"]);
+        assert!(game.is_in_check(Color::Black));
+        assert_eq!(game.legal_move_count(), 0);
+        assert!(game.is_checkmate());
+        assert!(!game.is_stalemate());
+        // in check, Kf2 is the only way out
+        let game = Game::from_fen("7k/8/8/8/8/8/6PP/r5K1 w - - 0 1").unwrap();
+        assert!(game.is_in_check(Color::White));
+        assert_eq!(game.legal_move_count(), 1);
+        assert!(game.has_any_legal_move());
+        // stalemated
+        let game = Game::from_fen("k7/2Q5/8/8/8/8/8/4K3 b - - 0 1").unwrap();
+        assert!(!game.has_any_legal_move());
+        assert!(game.is_stalemate());
+    }
+
+    #[test]
+    fn move_and_position_equality() {
+        let game = Game::default();
+        assert_eq!(game.parse_uci("e2e4"), game.parse_uci("e2e4"));
+        assert_ne!(game.parse_uci("e2e4"), game.parse_uci("e2e3"));
+        let game = Game::from_fen("4k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_ne!(game.parse_uci("a7a8q"), game.parse_uci("a7a8n"));
+
+        // same position by different routes and with different clocks
+        let mut a = Game::default();
+        play_uci(&mut a, &["g1f3", "g8f6", "b1c3"]);
+        let mut b = Game::default();
+        play_uci(&mut b, &["b1c3", "g8f6", "g1f3"]);
+        assert!(a.same_position_as(&b));
+        let c = Game::from_fen(&a.to_fen().replace(" 3 2", " 0 40")).unwrap();
+        assert_eq!(c.fullmove_number, 40);
+        assert!(a.same_position_as(&c));
+        // the side to move, castling and en passant all count
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w Kkq - 0 1",
+        ] {
+            assert!(!Game::default().same_position_as(&Game::from_fen(fen).unwrap()));
+        }
+        let mut d = Game::from_fen("4k3/8/8/8/3p4/8/4P3/4K3 w - - 0 1").unwrap();
+        play_uci(&mut d, &["e2e4"]);
+        let e = Game::from_fen("4k3/8/8/8/3pP3/8/8/4K3 b - - 0 1").unwrap();
+        assert!(!d.same_position_as(&e));
+    }
+
+    #[test]
+    fn capture_moves_counts() {
+        // the published perft capture counts at depth 1
+        for (fen, captures) in [
+            (PERFT_SUITE[0].fen, 0),
+            (PERFT_SUITE[1].fen, 8),
+            (PERFT_SUITE[2].fen, 1),
+        ] {
+            let game = Game::from_fen(fen).unwrap();
+            let capture_moves = game.capture_moves();
+            assert_eq!(capture_moves.len(), captures, "{}", fen);
+            let legal: Vec<&Move> = game.all_legal_moves().collect();
+            assert!(capture_moves.iter().all(|m| legal.contains(&m)));
+        }
+        // en passant counts even though the target square is empty
+        let game = Game::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+        let capture_moves = game.capture_moves();
+        assert_eq!(capture_moves.len(), 1);
+        assert_eq!(capture_moves[0].en_passant_capture, Some(sq("d5")));
+    }
+
+    #[test]
+    fn attacks_are_not_moves() {
+        let names = |squares: Vec<Square>| -> Vec<String> {
+            let mut names: Vec<String> = squares.iter().map(square_to_algebraic).collect();
+            names.sort();
+            names
+        };
+        let moves = |game: &Game, from: &str| {
+            names(
+                game.legal_moves_on_square(sq(from))
+                    .iter()
+                    .map(|m| m.to)
+                    .collect(),
+            )
+        };
+        // a pawn attacks the empty diagonals it can't move to, and not the squares it can
+        let game = Game::default();
+        assert_eq!(names(game.attacks_from(&sq("e2"))), ["d3", "f3"]);
+        assert_eq!(moves(&game, "e2"), ["e3", "e4"]);
+        // and a blocked pawn still attacks
+        let game = Game::from_fen("4k3/8/8/8/4p3/4P3/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(names(game.attacks_from(&sq("e3"))), ["d4", "f4"]);
+        assert_eq!(moves(&game, "e3"), Vec::<String>::new());
+        // nothing there, nothing attacked
+        assert!(game.attacks_from(&sq("a1")).is_empty());
+        // sliders stop on their own pieces too, defending them
+        let game = Game::default();
+        assert_eq!(names(game.attacks_from(&sq("a1"))), ["a2", "b1"]);
+        assert_eq!(moves(&game, "a1"), Vec::<String>::new());
+        // whoever's turn it is
+        assert_eq!(names(game.attacks_from(&sq("g8"))), ["e7", "f6", "h6"]);
+    }
+
+    #[test]
+    fn knight_pinned_by_bishop() {
+        let game = Game::from_fen("4k3/8/8/8/1b6/8/3N4/4K3 w - - 0 1").unwrap();
+        assert_eq!(game.pinned_pieces(Color::White), [(sq("d2"), sq("b4"))]);
+        assert_eq!(game.pinned_pieces(Color::Black), []);
+        assert!(game.legal_moves_on_square(sq("d2")).is_empty());
+        // a second piece in the way means no pin
+        let game = Game::from_fen("4k3/8/8/8/1b6/2P5/3N4/4K3 w - - 0 1").unwrap();
+        assert_eq!(game.pinned_pieces(Color::White), []);
+        // a piece of the bishop's own color in the way just blocks
+        let game = Game::from_fen("4k3/8/8/8/1b6/8/3n4/4K3 w - - 0 1").unwrap();
+        assert_eq!(game.pinned_pieces(Color::White), []);
+        assert_eq!(game.pinned_pieces(Color::Black), []);
+        // a rook pin along the file, with the rook free to move along it
+        let game = Game::from_fen("4r3/8/8/8/8/4R3/8/4K2k w - - 0 1").unwrap();
+        assert_eq!(game.pinned_pieces(Color::White), [(sq("e3"), sq("e8"))]);
+        assert!(game
+            .legal_moves_on_square(sq("e3"))
+            .iter()
+            .all(|m| m.to.1 == 4));
+    }
+
+    #[test]
+    fn null_move_round_trips() {
+        // with an en passant target for the null move to throw away
+        let mut game = Game::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+        let before = game.clone();
+        assert!(!game.unmake_null_move());
+        game.make_null_move();
+        assert_eq!(game.turn, Color::Black);
+        assert_eq!(game.en_passant_target_square, None);
+        assert_eq!(game.hash(), game.compute_hash());
+        assert_ne!(game.hash(), before.hash());
+        game.compute_legal_moves(true);
+        // a real move on top has to come off first
+        play_uci(&mut game, &["e8e7"]);
+        assert!(!game.unmake_null_move());
+        game.unmake_move();
+        assert!(game.unmake_null_move());
+        game.compute_legal_moves(true);
+        assert!(game.same_position_as(&before));
+        assert_eq!(game.hash(), before.hash());
+        assert!(game.is_legal(&sq("e5"), &sq("d6")));
+    }
+
+    #[test]
+    fn discovered_checks() {
+        // the knight is all that stands between the rook and the king
+        let game = Game::from_fen("4k3/8/8/8/4N3/8/8/4RK2 w - - 0 1").unwrap();
+        let gives_check = |text: &str| game.gives_check(&game.parse_uci(text).unwrap());
+        assert!(gives_check("e4c5"));
+        assert!(gives_check("e4g3"));
+        // the rook and the knight both
+        assert!(gives_check("e4f6"));
+        assert!(!gives_check("f1f2"));
+        assert!(!gives_check("e1d1"));
+        assert_eq!(game.move_to_san(&game.parse_uci("e4c5").unwrap()), "Nc5+");
+        // en passant can uncover a check along the diagonal
+        let game = Game::from_fen("7B/8/8/3pP3/8/8/8/k3K3 w - d6 0 1").unwrap();
+        let en_passant = game.parse_uci("e5d6").unwrap();
+        assert!(game.gives_check(&en_passant));
+        assert_eq!(game.move_to_san(&en_passant), "exd6+");
+    }
+
+    #[test]
+    fn capturing_a_home_rook_takes_its_castling_right() {
+        let fen = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+        let mut game = Game::from_fen(fen).unwrap();
+        play_uci(&mut game, &["h1h8"]);
+        // white's rook left home and black's was taken
+        assert_eq!(game.to_fen(), "r3k2R/8/8/8/8/8/8/R3K3 b Qq - 0 1");
+        assert_eq!(game.hash(), game.compute_hash());
+        game.unmake_move_and_recalculate();
+        assert_eq!(game.to_fen(), fen);
+        assert_eq!(game.hash(), game.compute_hash());
+        // same for black taking on a1
+        let mut game = Game::from_fen("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1").unwrap();
+        play_uci(&mut game, &["a8a1"]);
+        assert_eq!(game.to_fen(), "4k2r/8/8/8/8/8/8/r3K2R w Kk - 0 2");
+        // a rook taken away from its home square has no right to take, black keeps queenside
+        let mut game = Game::from_fen("r3k3/8/8/8/8/8/7r/R3K2R w KQq - 0 1").unwrap();
+        play_uci(&mut game, &["h1h2"]);
+        assert_eq!(game.to_fen(), "r3k3/8/8/8/8/8/7R/R3K3 b Qq - 0 1");
+    }
+
+    #[test]
+    fn material_counts() {
+        let game = Game::default();
+        // 8 pawns, 2 knights, 2 bishops, 2 rooks and a queen
+        assert_eq!(game.material_count(Color::White), 3_900);
+        assert_eq!(
+            game.material_count(Color::White) - game.material_count(Color::Black),
+            0
+        );
+        let mut game = Game::default();
+        play_uci(&mut game, &["e2e4", "d7d5", "e4d5", "d8d5"]);
+        assert_eq!(game.material_count(Color::White), 3_800);
+        assert_eq!(game.material_count(Color::Black), 3_800);
+        // promoted pieces count like any other
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/QQ2K3 w - - 0 1").unwrap();
+        assert_eq!(game.material_count(Color::White), 1_800);
+        assert_eq!(game.material_count(Color::Black), 0);
+    }
+
+    #[test]
+    fn reset_goes_back_to_the_start() {
+        let mut game = Game::default();
+        play_uci(&mut game, &["e2e4", "e7e5", "g1f3", "b8c6"]);
+        game.unmake_move_and_recalculate();
+        game.resign(Color::Black);
+        game.reset();
+        assert_eq!(game.to_fen(), Game::default().to_fen());
+        assert!(game.moves.is_empty() && game.redo.is_empty());
+        assert_eq!(game.result(), GameResult::Ongoing);
+        assert_eq!(game.repetitions(), 1);
+        assert_eq!(game.hash(), Game::default().hash());
+        assert_eq!(game.legal_move_count(), 20);
+        // the variant stays
+        let mut game = Game::default();
+        game.set_variant(Variant::Atomic);
+        play_uci(&mut game, &["e2e4"]);
+        game.reset();
+        assert_eq!(game.variant, Variant::Atomic);
+        assert_eq!(game.to_fen(), Game::default().to_fen());
+    }
+
+    #[test]
+    fn ascii_board_footer() {
+        let mut game = Game::default();
+        assert_eq!(
+            game.to_ascii(true),
+            "8 rnbqkbnr\n7 pppppppp\n6 ........\n5 ........\n\
+             4 ........\n3 ........\n2 PPPPPPPP\n1 RNBQKBNR\n  abcdefgh\nWhite to move\n"
+        );
+        assert!(!game.to_ascii(false).contains("to move"));
+        play_uci(&mut game, &["f2f3", "e7e5", "g2g4"]);
+        assert!(game.to_ascii(true).ends_with("Black to move\n"));
+        // once it's over the footer says how
+        play_uci(&mut game, &["d8h4"]);
+        assert!(game
+            .to_ascii(true)
+            .ends_with("  abcdefgh\nCheckmate, Black wins\n"));
+        game.unmake_move_and_recalculate();
+        game.resign(Color::Black);
+        assert!(game.to_ascii(true).ends_with("Black resigns, White wins\n"));
+    }
 }
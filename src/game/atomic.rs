@@ -0,0 +1,129 @@
+use super::{home_row, zobrist, Color, Game, Move, PieceType, KING_MOVES};
+
+impl Game {
+    // atomic chess: a capture blows up the capturing piece and every piece but pawns next to the
+    // target square. the removed pieces and any castling rights they took with them are written
+    // into the move so unmake_move can put them back
+    pub(super) fn explode(&mut self, mov: &mut Move) {
+        let center = mov.to;
+        for (row, col) in [(0, 0)].into_iter().chain(KING_MOVES) {
+            let square = (center.0 + row, center.1 + col);
+            let Some(piece) = *self.board.get(&square) else {
+                continue;
+            };
+            if square != center && piece.piece_type == PieceType::Pawn {
+                continue;
+            }
+            self.set_piece(&square, None);
+            mov.exploded.push((square, piece));
+        }
+
+        // a blown up king or rook on its home square can't castle anymore
+        let files = self.castling_files;
+        let keys = zobrist::keys();
+        self.zobrist ^= keys.castling(&self.castling_rights);
+        for (square, piece) in &mov.exploded {
+            if square.0 != home_row(piece.color) {
+                continue;
+            }
+            let (kingside, queenside) = match piece.piece_type {
+                PieceType::King if square.1 == files.king => (true, true),
+                PieceType::Rook if square.1 == files.kingside_rook => (true, false),
+                PieceType::Rook if square.1 == files.queenside_rook => (false, true),
+                _ => continue,
+            };
+            let rights = &mut self.castling_rights;
+            let losing = &mut mov.losing_castle_rights;
+            match piece.color {
+                Color::White => {
+                    losing.white_kingside |= kingside && rights.white_kingside;
+                    losing.white_queenside |= queenside && rights.white_queenside;
+                    rights.white_kingside &= !kingside;
+                    rights.white_queenside &= !queenside;
+                }
+                Color::Black => {
+                    losing.black_kingside |= kingside && rights.black_kingside;
+                    losing.black_queenside |= queenside && rights.black_queenside;
+                    rights.black_kingside &= !kingside;
+                    rights.black_queenside &= !queenside;
+                }
+            }
+        }
+        self.zobrist ^= keys.castling(&self.castling_rights);
+    }
+    // puts back what explode removed, the castling rights come back with the rest of the move
+    pub(super) fn unexplode(&mut self, mov: &Move) {
+        for (square, piece) in &mov.exploded {
+            self.set_piece(square, Some(*piece));
+        }
+    }
+    // the kings can't capture each other's neighbours without blowing themselves up, so side
+    // by side they can't be in check
+    pub(super) fn kings_touching(&self) -> bool {
+        match (
+            self.king_square(Color::White),
+            self.king_square(Color::Black),
+        ) {
+            (Some(white), Some(black)) => {
+                (white.0 - black.0).abs() <= 1 && (white.1 - black.1).abs() <= 1
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{GameResult, Variant};
+    use super::*;
+
+    fn atomic(fen: &str) -> Game {
+        let mut game = Game::from_fen(fen).unwrap();
+        game.set_variant(Variant::Atomic);
+        game
+    }
+
+    fn play(game: &mut Game, text: &str) {
+        let mov = game.parse_uci(text).unwrap();
+        assert!(
+            game.request_move(&mov.from, &mov.to),
+            "{} isn't legal",
+            text
+        );
+    }
+
+    #[test]
+    fn perft_from_the_start() {
+        let mut game = atomic(Game::default().to_fen().as_str());
+        let counts: Vec<u64> = (1..=4).map(|depth| game.perft(depth)).collect();
+        assert_eq!(counts, [20, 400, 8_902, 197_326]);
+    }
+
+    #[test]
+    fn captures_explode() {
+        // the pawns next to d5 survive, the bishop and both knights don't
+        let fen = "4k3/8/2p1b3/3n4/4P3/2N5/8/4K3 w - - 0 1";
+        let mut game = atomic(fen);
+        play(&mut game, "c3d5");
+        assert_eq!(game.to_fen(), "4k3/8/2p5/8/4P3/8/8/4K3 b - - 0 1");
+        assert_eq!(game.hash(), game.compute_hash());
+        game.unmake_move_and_recalculate();
+        assert_eq!(game.to_fen(), fen);
+        assert_eq!(game.hash(), game.compute_hash());
+    }
+
+    #[test]
+    fn exploding_the_king_wins() {
+        let mut game = atomic("r3k3/4q3/8/8/8/8/8/4R1K1 w q - 0 1");
+        play(&mut game, "e1e7");
+        assert_eq!(game.king_square(Color::Black), None);
+        assert_eq!(game.result(), GameResult::Explosion(Color::White));
+        // over, black doesn't get to play on
+        assert!(!game.request_move(&(0, 0), &(0, 1)));
+        // the king took its castling right with it, and gets it back on undo
+        assert!(!game.castling_rights.black_queenside);
+        game.unmake_move_and_recalculate();
+        assert!(game.castling_rights.black_queenside);
+        assert_eq!(game.result(), GameResult::Ongoing);
+    }
+}
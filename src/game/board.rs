@@ -0,0 +1,57 @@
+use std::ops::{Index, IndexMut};
+
+use serde::{Deserialize, Serialize};
+
+use super::{Piece, Square, SquareExt};
+
+// just the pieces, cheap to copy so a position can be snapshotted without the rest of Game.
+// row 0 is rank 8, same as everywhere else
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default, Serialize, Deserialize)]
+pub struct Board(pub [[Option<Piece>; 8]; 8]);
+
+impl Board {
+    // None for empty and off the board squares
+    pub fn get(&self, square: &Square) -> &Option<Piece> {
+        match square.to_indices() {
+            Some((row, col)) => &self.0[row][col],
+            None => &None,
+        }
+    }
+    // panics if the square is off the board
+    pub fn set(&mut self, square: &Square, piece: Option<Piece>) {
+        let (row, col) = square.to_indices().expect("square is off the board");
+        self.0[row][col] = piece;
+    }
+    // whatever was on `to` is overwritten
+    pub fn move_piece(&mut self, from: &Square, to: &Square) {
+        let piece = *self.get(from);
+        self.set(from, None);
+        self.set(to, piece);
+    }
+    // rows from rank 8 down to rank 1
+    pub fn iter(&self) -> std::slice::Iter<'_, [Option<Piece>; 8]> {
+        self.0.iter()
+    }
+    // every occupied square, in the same order as iter
+    pub fn pieces(&self) -> impl Iterator<Item = (Square, Piece)> + '_ {
+        self.0.iter().enumerate().flat_map(|(row, prow)| {
+            prow.iter()
+                .enumerate()
+                .filter_map(move |(col, piece)| piece.map(|p| (Square::from_indices(row, col), p)))
+        })
+    }
+}
+
+// board[row][col] like the plain array it replaced
+impl Index<usize> for Board {
+    type Output = [Option<Piece>; 8];
+    fn index(&self, row: usize) -> &Self::Output {
+        &self.0[row]
+    }
+}
+
+impl IndexMut<usize> for Board {
+    fn index_mut(&mut self, row: usize) -> &mut Self::Output {
+        &mut self.0[row]
+    }
+}
@@ -0,0 +1,212 @@
+use super::{starting_board, CastlingFiles, Color, Game, PieceType};
+
+// knight placements over the five squares left after the bishops and queen, by index
+const KNIGHT_PLACEMENTS: [(usize, usize); 10] = [
+    (0, 1),
+    (0, 2),
+    (0, 3),
+    (0, 4),
+    (1, 2),
+    (1, 3),
+    (1, 4),
+    (2, 3),
+    (2, 4),
+    (3, 4),
+];
+
+// the back row for a start position number, using the standard numbering
+fn back_row(position_id: u16) -> [PieceType; 8] {
+    let mut row: [Option<PieceType>; 8] = [None; 8];
+    let mut n = position_id as usize;
+    // light squared bishop on b, d, f or h, then the dark squared one on a, c, e or g
+    row[(n % 4) * 2 + 1] = Some(PieceType::Bishop);
+    n /= 4;
+    row[(n % 4) * 2] = Some(PieceType::Bishop);
+    n /= 4;
+    // the queen and knights go on the nth empty square
+    let empty = |row: &[Option<PieceType>; 8]| -> Vec<usize> {
+        (0..8).filter(|col| row[*col].is_none()).collect()
+    };
+    row[empty(&row)[n % 6]] = Some(PieceType::Queen);
+    n /= 6;
+    let (first, second) = KNIGHT_PLACEMENTS[n];
+    let remaining = empty(&row);
+    row[remaining[first]] = Some(PieceType::Knight);
+    row[remaining[second]] = Some(PieceType::Knight);
+    // the king always ends up between the rooks
+    for (col, piece_type) in
+        empty(&row)
+            .into_iter()
+            .zip([PieceType::Rook, PieceType::King, PieceType::Rook])
+    {
+        row[col] = Some(piece_type);
+    }
+    row.map(|p| p.expect("every square is filled"))
+}
+
+impl Game {
+    // fischer random start position 0 to 959, 518 is the standard setup. panics past 959
+    pub fn from_chess960(position_id: u16) -> Game {
+        assert!(
+            position_id < 960,
+            "chess960 positions are numbered 0 to 959, got {}",
+            position_id
+        );
+        let row = back_row(position_id);
+        let cols =
+            |piece_type: PieceType| (0i8..8).filter(move |col| row[*col as usize] == piece_type);
+        let mut rooks = cols(PieceType::Rook);
+        let castling_files = CastlingFiles {
+            king: cols(PieceType::King).next().unwrap(),
+            queenside_rook: rooks.next().unwrap(),
+            kingside_rook: rooks.next().unwrap(),
+        };
+        Game::from_position(
+            starting_board(row),
+            Color::White,
+            Default::default(), // all true
+            castling_files,
+            None,
+            0,
+            1,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{algebraic_to_square, Castling, Move, Piece};
+    use super::*;
+
+    #[test]
+    fn standard_numbering() {
+        assert_eq!(Game::from_chess960(518).to_fen(), Game::default().to_fen());
+        let name = |id| {
+            back_row(id)
+                .map(|piece_type| {
+                    Piece {
+                        piece_type,
+                        color: Color::White,
+                    }
+                    .to_fen_char()
+                })
+                .iter()
+                .collect::<String>()
+        };
+        assert_eq!(name(0), "BBQNNRKR");
+        assert_eq!(name(959), "RKRNNQBB");
+        let mut rows: Vec<String> = (0..960).map(name).collect();
+        rows.sort();
+        rows.dedup();
+        assert_eq!(rows.len(), 960);
+    }
+
+    // the start position with white's back rank cleared down to the king and rooks
+    fn cleared(id: u16) -> Game {
+        let mut game = Game::from_chess960(id);
+        for col in 0..8 {
+            if !matches!(
+                game.board.get(&(7, col)).map(|p| p.piece_type),
+                Some(PieceType::King | PieceType::Rook)
+            ) {
+                game.board.set(&(7, col), None);
+            }
+        }
+        game.compute_legal_moves(true);
+        game
+    }
+
+    #[test]
+    fn castling_in_every_layout() {
+        for id in 0..960 {
+            let mut game = cleared(id);
+            let before = game.board;
+            let files = game.castling_files;
+            let castles: Vec<Move> = game
+                .legal_moves_on_square((7, files.king))
+                .iter()
+                .filter(|m| m.castle.is_some())
+                .cloned()
+                .collect();
+            // everything from where the king and rook start to where they land has to be empty,
+            // which the other rook can be in the way of. like f1 in BBQNNRKR
+            let clear = |cols: [i8; 4], other_rook: i8| {
+                !(*cols.iter().min().unwrap()..=*cols.iter().max().unwrap()).contains(&other_rook)
+            };
+            let expected = [
+                clear(
+                    [files.king, 6, files.kingside_rook, 5],
+                    files.queenside_rook,
+                ),
+                clear(
+                    [files.king, 2, files.queenside_rook, 3],
+                    files.kingside_rook,
+                ),
+            ];
+            assert_eq!(
+                castles.len(),
+                expected.iter().filter(|c| **c).count(),
+                "position {}",
+                id
+            );
+            for mov in castles {
+                // the king and rook end up where they would in standard chess
+                let (king_col, rook_col) = match mov.castle {
+                    Some(Castling::WhiteKingside) => (6, 5),
+                    _ => (2, 3),
+                };
+                game.make_move(&mov);
+                let at = |col| game.board.get(&(7, col)).map(|p| p.piece_type);
+                assert_eq!(at(king_col), Some(PieceType::King), "position {}", id);
+                assert_eq!(at(rook_col), Some(PieceType::Rook), "position {}", id);
+                assert_eq!(game.board.pieces().filter(|(s, _)| s.0 == 7).count(), 3);
+                game.unmake_move();
+                assert!(game.board == before, "position {}", id);
+            }
+        }
+    }
+
+    // castles are played by the king taking its own rook, since where the king lands can be a
+    // plain king move or where it already stands
+    #[test]
+    fn castling_onto_the_rook() {
+        let square = |name| algebraic_to_square(name).unwrap();
+        for (id, uci, king_to, rook_to) in [
+            // QBNNRKBR, king on f1 next to g1, with the h1 rook
+            (12, "f1h1", "g1", "f1"),
+            (12, "f1e1", "c1", "d1"),
+            // RKBBQNNR, the mirror with the king on b1 next to c1 and the a1 rook
+            (709, "b1a1", "c1", "d1"),
+            // BQNNRBKR and NRKBBQNR, the king already on g1 or c1 doesn't move at all
+            (2, "g1h1", "g1", "f1"),
+            (249, "c1b1", "c1", "d1"),
+        ] {
+            let mut game = cleared(id);
+            let mov = game.parse_uci(uci).unwrap();
+            assert!(mov.castle.is_some(), "{} in {}", uci, id);
+            assert_eq!(mov.to, square(king_to));
+            assert_eq!(mov.to_uci(), uci);
+            let (from, rook) = (square(&uci[..2]), square(&uci[2..]));
+            // a plain step onto the castling square stays a plain step
+            if (from.1 - mov.to.1).abs() == 1 {
+                assert!(game.request_move(&from, &mov.to), "{} in {}", uci, id);
+                let step = game.moves.last().unwrap();
+                assert_eq!(step.castle, None);
+                assert_eq!(step.to_uci(), format!("{}{}", &uci[..2], king_to));
+                game.unmake_move_and_recalculate();
+            }
+            assert!(game.request_move(&from, &rook), "{} in {}", uci, id);
+            assert_eq!(game.moves.last(), Some(&mov));
+            let at = |name| game.board.get(&square(name)).map(|p| p.piece_type);
+            assert_eq!(at(king_to), Some(PieceType::King), "{} in {}", uci, id);
+            assert_eq!(at(rook_to), Some(PieceType::Rook), "{} in {}", uci, id);
+        }
+        // the standard layout keeps standard notation
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1").unwrap();
+        assert_eq!(
+            game.parse_uci("e1g1").unwrap().castle,
+            Some(Castling::WhiteKingside)
+        );
+        assert_eq!(game.parse_uci("e1h1"), None);
+    }
+}
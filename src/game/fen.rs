@@ -0,0 +1,366 @@
+use std::fmt::{Display, Formatter};
+
+use super::setup::check_board;
+use super::{
+    algebraic_to_square, square_to_algebraic, Board, CastlingRights, Color, Game, Piece,
+    SetupError, Square,
+};
+
+#[derive(Debug, PartialEq)]
+pub enum FenError {
+    // a fen has four to six space separated fields, the clocks can be left off
+    FieldCount(usize),
+    // the placement field needs eight ranks separated by slashes
+    RankCount(usize),
+    // a rank that doesn't add up to eight squares, by rank number
+    RankLength(u8),
+    BadPiece(char),
+    BadTurn(String),
+    BadCastling(String),
+    BadEnPassant(String),
+    BadHalfmoveClock(String),
+    BadFullmoveNumber(String),
+    // well formed, but not a position that can be played from
+    Setup(SetupError),
+}
+
+impl Display for FenError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FenError::FieldCount(n) => write!(f, "expected 4 to 6 fields, found {}", n),
+            FenError::RankCount(n) => write!(f, "expected 8 ranks, found {}", n),
+            FenError::RankLength(rank) => write!(f, "rank {} does not have 8 squares", rank),
+            FenError::BadPiece(c) => write!(f, "invalid piece character '{}'", c),
+            FenError::BadTurn(s) => write!(f, "invalid side to move \"{}\"", s),
+            FenError::BadCastling(s) => write!(f, "invalid castling availability \"{}\"", s),
+            FenError::BadEnPassant(s) => write!(f, "invalid en passant square \"{}\"", s),
+            FenError::BadHalfmoveClock(s) => write!(f, "invalid halfmove clock \"{}\"", s),
+            FenError::BadFullmoveNumber(s) => write!(f, "invalid fullmove number \"{}\"", s),
+            FenError::Setup(e) => write!(f, "impossible position: {}", e),
+        }
+    }
+}
+
+impl From<SetupError> for FenError {
+    fn from(e: SetupError) -> Self {
+        FenError::Setup(e)
+    }
+}
+
+// en passant targets can only be on the 3rd or 6th rank
+fn parse_en_passant(s: &str) -> Option<Square> {
+    algebraic_to_square(s).filter(|(row, _)| *row == 2 || *row == 5)
+}
+
+impl Game {
+    pub fn from_fen(fen: &str) -> Result<Game, FenError> {
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        if !(4..=6).contains(&fields.len()) {
+            return Err(FenError::FieldCount(fields.len()));
+        }
+        // puzzle collections often leave off the clocks, start them fresh
+        let halfmove_field = fields.get(4).copied().unwrap_or("0");
+        let fullmove_field = fields.get(5).copied().unwrap_or("1");
+
+        // piece placement, from rank 8 (row 0) down to rank 1
+        let mut board = Board::default();
+        let ranks: Vec<&str> = fields[0].split('/').collect();
+        if ranks.len() != 8 {
+            return Err(FenError::RankCount(ranks.len()));
+        }
+        for (row, rank) in ranks.iter().enumerate() {
+            let rank_number = 8 - row as u8;
+            let mut col = 0usize;
+            for c in rank.chars() {
+                if let Some(empty) = c.to_digit(10) {
+                    if !(1..=8).contains(&empty) {
+                        return Err(FenError::RankLength(rank_number));
+                    }
+                    col += empty as usize;
+                } else {
+                    let piece = Piece::from_fen_char(c).ok_or(FenError::BadPiece(c))?;
+                    if col >= 8 {
+                        return Err(FenError::RankLength(rank_number));
+                    }
+                    board[row][col] = Some(piece);
+                    col += 1;
+                }
+                if col > 8 {
+                    return Err(FenError::RankLength(rank_number));
+                }
+            }
+            if col != 8 {
+                return Err(FenError::RankLength(rank_number));
+            }
+        }
+
+        let turn = match fields[1] {
+            "w" => Color::White,
+            "b" => Color::Black,
+            other => return Err(FenError::BadTurn(other.to_string())),
+        };
+
+        let mut castling_rights = CastlingRights {
+            white_queenside: false,
+            white_kingside: false,
+            black_queenside: false,
+            black_kingside: false,
+        };
+        if fields[2] != "-" {
+            for c in fields[2].chars() {
+                let right = match c {
+                    'K' => &mut castling_rights.white_kingside,
+                    'Q' => &mut castling_rights.white_queenside,
+                    'k' => &mut castling_rights.black_kingside,
+                    'q' => &mut castling_rights.black_queenside,
+                    _ => return Err(FenError::BadCastling(fields[2].to_string())),
+                };
+                *right = true;
+            }
+        }
+
+        let en_passant_target_square = match fields[3] {
+            "-" => None,
+            s => Some(parse_en_passant(s).ok_or(FenError::BadEnPassant(s.to_string()))?),
+        };
+
+        let halfmove_clock: u8 = halfmove_field
+            .parse()
+            .map_err(|_| FenError::BadHalfmoveClock(halfmove_field.to_string()))?;
+        // move numbers start at 1
+        let fullmove_number: u16 = match fullmove_field.parse() {
+            Ok(n) if n >= 1 => n,
+            _ => return Err(FenError::BadFullmoveNumber(fullmove_field.to_string())),
+        };
+
+        // before from_position, which generates the moves
+        check_board(&board)?;
+        let game = Game::from_position(
+            board,
+            turn,
+            castling_rights,
+            Default::default(),
+            en_passant_target_square,
+            halfmove_clock,
+            fullmove_number,
+        );
+        game.check_opponent_not_in_check()?;
+        Ok(game)
+    }
+    pub fn to_fen(&self) -> String {
+        let mut fen = String::new();
+        for (row, prow) in self.board.iter().enumerate() {
+            if row > 0 {
+                fen.push('/');
+            }
+            // run length of empty squares
+            let mut empty = 0;
+            for square in prow {
+                match square {
+                    Some(p) => {
+                        if empty > 0 {
+                            fen.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        fen.push(p.to_fen_char());
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                fen.push_str(&empty.to_string());
+            }
+        }
+
+        fen.push_str(match self.turn {
+            Color::White => " w ",
+            Color::Black => " b ",
+        });
+
+        let rights = &self.castling_rights;
+        let mut castling = String::new();
+        for (right, c) in [
+            (rights.white_kingside, 'K'),
+            (rights.white_queenside, 'Q'),
+            (rights.black_kingside, 'k'),
+            (rights.black_queenside, 'q'),
+        ] {
+            if right {
+                castling.push(c);
+            }
+        }
+        if castling.is_empty() {
+            castling.push('-');
+        }
+        fen.push_str(&castling);
+
+        fen.push(' ');
+        match self.en_passant_target_square {
+            Some(square) => fen.push_str(&square_to_algebraic(&square)),
+            None => fen.push('-'),
+        }
+
+        fen.push_str(&format!(
+            " {} {}",
+            self.halfmove_clock, self.fullmove_number
+        ));
+        fen
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Castling, PieceType, PERFT_SUITE};
+    use super::*;
+
+    const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    #[test]
+    fn parses_every_field() {
+        let game = Game::from_fen("r3k2r/8/8/3pP3/8/8/8/R3K2R w Kq d6 7 42").unwrap();
+        assert_eq!(
+            *game.piece_at_square(&(0, 0)),
+            Some(Piece {
+                piece_type: PieceType::Rook,
+                color: Color::Black
+            })
+        );
+        assert_eq!(
+            *game.piece_at_square(&(3, 4)),
+            Some(Piece {
+                piece_type: PieceType::Pawn,
+                color: Color::White
+            })
+        );
+        assert_eq!(game.turn, Color::White);
+        assert!(game.castling_rights.white_kingside);
+        assert!(!game.castling_rights.white_queenside);
+        assert!(!game.castling_rights.black_kingside);
+        assert!(game.castling_rights.black_queenside);
+        assert_eq!(game.en_passant_target_square, Some((2, 3)));
+        assert_eq!(game.halfmove_clock, 7);
+        assert_eq!(game.fullmove_number, 42);
+        // legal moves are ready to use
+        assert!(game.is_legal(&(3, 4), &(2, 3)));
+    }
+
+    #[test]
+    fn start_position_matches_default() {
+        let game = Game::from_fen(START).unwrap();
+        assert!(game.same_position_as(&Game::default()));
+        assert_eq!(game.zobrist, Game::default().zobrist);
+        assert_eq!(game.legal_move_count(), 20);
+    }
+
+    #[test]
+    fn round_trips() {
+        for fen in [START].into_iter().chain(PERFT_SUITE.iter().map(|p| p.fen)) {
+            assert_eq!(Game::from_fen(fen).unwrap().to_fen(), fen);
+        }
+        // and from a position reached by playing
+        let mut game = Game::default();
+        for (from, to) in [((6, 4), (4, 4)), ((1, 2), (3, 2)), ((7, 6), (5, 5))] {
+            assert!(game.request_move(&from, &to));
+        }
+        let fen = game.to_fen();
+        assert_eq!(
+            fen,
+            "rnbqkbnr/pp1ppppp/8/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2"
+        );
+        assert_eq!(Game::from_fen(&fen).unwrap().to_fen(), fen);
+    }
+
+    #[test]
+    fn malformed_fields_are_errors() {
+        let cases = [
+            ("8/8/8/8/8/8/8/k6K w -", FenError::FieldCount(3)),
+            ("8/8/8/8/8/8/k6K w - - 0 1", FenError::RankCount(7)),
+            ("8/8/8/8/8/8/8/k5K w - - 0 1", FenError::RankLength(1)),
+            ("9/8/8/8/8/8/8/k6K w - - 0 1", FenError::RankLength(8)),
+            ("8/8/8/8/8/8/8/k6KP w - - 0 1", FenError::RankLength(1)),
+            ("8/8/8/8/8/8/8/k6X w - - 0 1", FenError::BadPiece('X')),
+            ("8/8/8/8/8/8/8/k6K x - - 0 1", FenError::BadTurn("x".into())),
+            (
+                "8/8/8/8/8/8/8/k6K w KX - 0 1",
+                FenError::BadCastling("KX".into()),
+            ),
+            (
+                "8/8/8/8/8/8/8/k6K w - e4 0 1",
+                FenError::BadEnPassant("e4".into()),
+            ),
+            (
+                "8/8/8/8/8/8/8/k6K w - - -1 1",
+                FenError::BadHalfmoveClock("-1".into()),
+            ),
+            (
+                "8/8/8/8/8/8/8/k6K w - - 300 1",
+                FenError::BadHalfmoveClock("300".into()),
+            ),
+            (
+                "8/8/8/8/8/8/8/k6K w - - 0 0",
+                FenError::BadFullmoveNumber("0".into()),
+            ),
+        ];
+        for (fen, error) in cases {
+            assert_eq!(Game::from_fen(fen).err(), Some(error), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn impossible_positions_are_errors() {
+        let cases = [
+            (
+                "P7/8/8/8/8/8/8/k6K w - - 0 1",
+                SetupError::PawnOnBackRank((0, 0)),
+            ),
+            (
+                "8/8/8/8/8/8/8/k7 b - - 0 1",
+                SetupError::KingCount(Color::White, 0),
+            ),
+            (
+                "k7/8/8/8/8/8/8/k6K w - - 0 1",
+                SetupError::KingCount(Color::Black, 2),
+            ),
+            (
+                "k7/8/8/8/8/8/8/R6K w - - 0 1",
+                SetupError::OpponentInCheck(Color::Black),
+            ),
+        ];
+        for (fen, error) in cases {
+            assert_eq!(
+                Game::from_fen(fen).err(),
+                Some(FenError::Setup(error)),
+                "{}",
+                fen
+            );
+        }
+    }
+
+    #[test]
+    fn partial_fields() {
+        // no clocks, or only the halfmove one
+        let game = Game::from_fen("4k3/8/8/8/8/8/4P3/4K3 w - -").unwrap();
+        assert_eq!((game.halfmove_clock, game.fullmove_number), (0, 1));
+        assert_eq!(game.to_fen(), "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1");
+        let game = Game::from_fen("4k3/8/8/8/8/8/4P3/4K3 b - - 12").unwrap();
+        assert_eq!((game.halfmove_clock, game.fullmove_number), (12, 1));
+
+        // only the castles the field names are offered
+        let game = Game::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w Kq -").unwrap();
+        let castles = |game: &Game, king: Square| -> Vec<Castling> {
+            game.legal_moves_on_square(king)
+                .iter()
+                .filter_map(|m| m.castle)
+                .collect()
+        };
+        assert_eq!(castles(&game, (7, 4)), [Castling::WhiteKingside]);
+        assert_eq!(game.to_fen(), "r3k2r/8/8/8/8/8/8/R3K2R w Kq - 0 1");
+        let game = Game::from_fen("r3k2r/8/8/8/8/8/8/R3K2R b Kq -").unwrap();
+        assert_eq!(castles(&game, (0, 4)), [Castling::BlackQueenside]);
+        let game = Game::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w - -").unwrap();
+        assert_eq!(castles(&game, (7, 4)), []);
+        // a right with no rook to back it up is dropped
+        let game = Game::from_fen("r3k3/8/8/8/8/8/8/4K2R w KQkq -").unwrap();
+        assert_eq!(game.to_fen(), "r3k3/8/8/8/8/8/8/4K2R w Kq - 0 1");
+    }
+}
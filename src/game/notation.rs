@@ -0,0 +1,256 @@
+use super::{algebraic_to_square, square_to_algebraic, Castling, Game, Move, PieceType, Square};
+
+// uppercase letter used for a piece in SAN, pawns have none
+fn piece_letter(piece_type: PieceType) -> Option<char> {
+    match piece_type {
+        PieceType::Pawn => None,
+        PieceType::Knight => Some('N'),
+        PieceType::Bishop => Some('B'),
+        PieceType::Rook => Some('R'),
+        PieceType::Queen => Some('Q'),
+        PieceType::King => Some('K'),
+    }
+}
+
+impl Game {
+    // finds the legal move for coordinate notation like e2e4 or e7e8q
+    pub fn parse_uci(&self, s: &str) -> Option<Move> {
+        if !s.is_ascii() || !(4..=5).contains(&s.len()) {
+            return None;
+        }
+        let from = algebraic_to_square(&s[0..2])?;
+        let to = algebraic_to_square(&s[2..4])?;
+        let promotion = match s[4..].chars().next() {
+            None => None,
+            Some('q') => Some(PieceType::Queen),
+            Some('r') => Some(PieceType::Rook),
+            Some('b') => Some(PieceType::Bishop),
+            Some('n') => Some(PieceType::Knight),
+            Some(_) => return None,
+        };
+        self.legal_moves_on_square(from)
+            .iter()
+            .find(|m| m.target() == to && m.promotion == promotion)
+            .cloned()
+    }
+    // finds the legal move for a SAN token like Nbd7, exd5, e8=Q+ or O-O
+    pub fn parse_san(&self, s: &str) -> Option<Move> {
+        // check, mate and annotation suffixes don't change which move it is
+        let s = s.trim_end_matches(['+', '#', '!', '?']);
+        if !s.is_ascii() {
+            return None;
+        }
+        let castle = match s {
+            "O-O" | "0-0" => Some(true),
+            "O-O-O" | "0-0-0" => Some(false),
+            _ => None,
+        };
+        if let Some(kingside) = castle {
+            return self
+                .all_legal_moves()
+                .find(|m| match m.castle {
+                    Some(Castling::WhiteKingside | Castling::BlackKingside) => kingside,
+                    Some(Castling::WhiteQueenside | Castling::BlackQueenside) => !kingside,
+                    None => false,
+                })
+                .cloned();
+        }
+
+        let mut rest = s;
+        let piece_type = match rest.chars().next()? {
+            'N' => PieceType::Knight,
+            'B' => PieceType::Bishop,
+            'R' => PieceType::Rook,
+            'Q' => PieceType::Queen,
+            'K' => PieceType::King,
+            _ => PieceType::Pawn,
+        };
+        if piece_type != PieceType::Pawn {
+            rest = &rest[1..];
+        }
+        // promotions, with or without the =
+        let mut promotion = None;
+        if piece_type == PieceType::Pawn {
+            let promoted = match rest.chars().last()? {
+                'N' => Some(PieceType::Knight),
+                'B' => Some(PieceType::Bishop),
+                'R' => Some(PieceType::Rook),
+                'Q' => Some(PieceType::Queen),
+                _ => None,
+            };
+            if promoted.is_some() {
+                promotion = promoted;
+                rest = rest[..rest.len() - 1].trim_end_matches('=');
+            }
+        }
+        if rest.len() < 2 {
+            return None;
+        }
+        let to = algebraic_to_square(&rest[rest.len() - 2..])?;
+        // whatever is left is the capture marker and disambiguation
+        let mut from_file = None;
+        let mut from_rank = None;
+        for c in rest[..rest.len() - 2].chars() {
+            match c {
+                'a'..='h' => from_file = Some(c as i8 - 'a' as i8),
+                '1'..='8' => from_rank = Some(8 - (c as i8 - '0' as i8)),
+                'x' => {}
+                _ => return None,
+            }
+        }
+
+        let mut candidates = self
+            .all_legal_moves()
+            .filter(|m| m.to == to && m.promotion == promotion && m.castle.is_none())
+            .filter(|m| from_file.is_none_or(|col| m.from.1 == col))
+            .filter(|m| from_rank.is_none_or(|row| m.from.0 == row))
+            .filter(|m| self.piece_at_square(&m.from).map(|p| p.piece_type) == Some(piece_type));
+        let mov = candidates.next()?;
+        // an ambiguous token doesn't name a move
+        match candidates.next() {
+            Some(_) => None,
+            None => Some(mov.clone()),
+        }
+    }
+    // SAN for every move played followed by the undone ones redo would play back, in order
+    pub fn san_line(&self) -> Vec<String> {
+        let mut replay = self.clone();
+        while replay.unmake_move() {}
+        replay.compute_legal_moves(true);
+        self.moves
+            .iter()
+            .chain(self.redo.iter().rev())
+            .map(|mov| {
+                let san = replay.move_to_san(mov);
+                replay.make_move(mov);
+                replay.compute_legal_moves(true);
+                san
+            })
+            .collect()
+    }
+    // standard algebraic notation for a legal move in the current position
+    pub fn move_to_san(&self, mov: &Move) -> String {
+        let mut san = match mov.castle {
+            Some(Castling::WhiteKingside | Castling::BlackKingside) => "O-O".to_string(),
+            Some(Castling::WhiteQueenside | Castling::BlackQueenside) => "O-O-O".to_string(),
+            None => {
+                let mut san = String::new();
+                // legal moves always start on a piece
+                let piece = self.piece_at_square(&mov.from).expect("no piece to move");
+                match piece_letter(piece.piece_type) {
+                    // pawn captures are named by the file they come from
+                    None => {
+                        if mov.capture.is_some() {
+                            san.push(square_to_algebraic(&mov.from).remove(0));
+                        }
+                    }
+                    Some(letter) => {
+                        san.push(letter);
+                        // other pieces of the same type that can also reach the target
+                        let others: Vec<Square> = self
+                            .all_legal_moves()
+                            .filter(|m| m.to == mov.to && m.from != mov.from)
+                            .filter(|m| {
+                                self.piece_at_square(&m.from).map(|p| p.piece_type)
+                                    == Some(piece.piece_type)
+                            })
+                            .map(|m| m.from)
+                            .collect();
+                        if !others.is_empty() {
+                            let name = square_to_algebraic(&mov.from);
+                            if !others.iter().any(|s| s.1 == mov.from.1) {
+                                san.push_str(&name[..1]);
+                            } else if !others.iter().any(|s| s.0 == mov.from.0) {
+                                san.push_str(&name[1..]);
+                            } else {
+                                san.push_str(&name);
+                            }
+                        }
+                    }
+                }
+                if mov.capture.is_some() {
+                    san.push('x');
+                }
+                san.push_str(&square_to_algebraic(&mov.to));
+                if let Some(letter) = mov.promotion.and_then(piece_letter) {
+                    san.push('=');
+                    san.push(letter);
+                }
+                san
+            }
+        };
+
+        // telling mate from check needs the replies in the position after the move
+        if self.gives_check(mov) {
+            let mut after = self.clone();
+            after.make_move(mov);
+            after.compute_legal_moves(true);
+            san.push(if after.has_any_legal_move() { '+' } else { '#' });
+        }
+        san
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // SAN for a move given in coordinates, and back again
+    fn san(fen: &str, uci: &str) -> String {
+        let game = Game::from_fen(fen).unwrap();
+        let mov = game.parse_uci(uci).unwrap();
+        let san = game.move_to_san(&mov);
+        assert_eq!(game.parse_san(&san), Some(mov), "{}", san);
+        san
+    }
+
+    #[test]
+    fn san_disambiguates() {
+        // by file, by rank, and by both when neither is enough
+        let fen = "4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1";
+        assert_eq!(san(fen, "b1d2"), "Nbd2");
+        assert_eq!(san(fen, "f1d2"), "Nfd2");
+        assert_eq!(san(fen, "f1g3"), "Ng3");
+        let fen = "4k3/8/8/N7/8/8/8/N3K3 w - - 0 1";
+        assert_eq!(san(fen, "a1b3"), "N1b3");
+        assert_eq!(san(fen, "a5b3"), "N5b3");
+        let fen = "4k3/8/8/8/8/Q7/8/Q1Q1K3 w - - 0 1";
+        assert_eq!(san(fen, "a1b2"), "Qa1b2");
+        assert_eq!(san(fen, "c1b2"), "Qcb2");
+    }
+
+    #[test]
+    fn san_captures_promotions_and_checks() {
+        let fen = "r3k3/1P6/8/8/8/8/8/4K3 w - - 0 1";
+        assert_eq!(san(fen, "b7a8q"), "bxa8=Q+");
+        assert_eq!(san(fen, "b7a8n"), "bxa8=N");
+        assert_eq!(san(fen, "b7b8r"), "b8=R+");
+        let scholars = "r1bqkbnr/pppp1ppp/2n5/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 2 3";
+        assert_eq!(san(scholars, "h5f7"), "Qxf7#");
+        assert_eq!(san(scholars, "c4f7"), "Bxf7+");
+        let castles = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+        assert_eq!(san(castles, "e1g1"), "O-O");
+        assert_eq!(san(castles, "e1c1"), "O-O-O");
+    }
+
+    #[test]
+    fn parse_uci_moves() {
+        let game = Game::default();
+        let mov = game.parse_uci("e2e4").unwrap();
+        assert_eq!((mov.from, mov.to), ((6, 4), (4, 4)));
+        assert_eq!(mov.to_uci(), "e2e4");
+        for illegal in ["e2e5", "e7e5", "e1g1", "e2e4q", "e2", "e2e4e4", "z9e4", ""] {
+            assert_eq!(game.parse_uci(illegal), None, "{}", illegal);
+        }
+
+        let game = Game::from_fen("4k3/P7/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        let mov = game.parse_uci("a7a8n").unwrap();
+        assert_eq!(mov.promotion, Some(PieceType::Knight));
+        assert_eq!(mov.to_uci(), "a7a8n");
+        // a promotion has to say what it promotes to
+        assert_eq!(game.parse_uci("a7a8"), None);
+        assert_eq!(game.parse_uci("a7a8k"), None);
+        let mov = game.parse_uci("e1g1").unwrap();
+        assert_eq!(mov.castle, Some(Castling::WhiteKingside));
+    }
+}
@@ -0,0 +1,154 @@
+use super::{Game, Move};
+
+pub struct PerftPosition {
+    pub name: &'static str,
+    pub fen: &'static str,
+    pub depth: u8,
+    pub nodes: u64,
+}
+
+// the usual engine test positions with their published counts, between them they cover en
+// passant, castling through and out of check, promotions and pins. deep enough to catch those
+// without taking forever
+pub const PERFT_SUITE: [PerftPosition; 5] = [
+    PerftPosition {
+        name: "initial",
+        fen: "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        depth: 5,
+        nodes: 4_865_609,
+    },
+    PerftPosition {
+        name: "kiwipete",
+        fen: "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        depth: 4,
+        nodes: 4_085_603,
+    },
+    PerftPosition {
+        name: "position 3",
+        fen: "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        depth: 5,
+        nodes: 674_624,
+    },
+    PerftPosition {
+        name: "position 4",
+        fen: "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        depth: 4,
+        nodes: 422_333,
+    },
+    PerftPosition {
+        name: "talkchess",
+        fen: "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        depth: 4,
+        nodes: 2_103_487,
+    },
+];
+
+impl Game {
+    // counts leaf nodes of the legal move tree, for checking move generation against known totals
+    pub fn perft(&mut self, depth: u8) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        // legal_moves is already validated for king safety
+        if depth == 1 {
+            return self.legal_move_count() as u64;
+        }
+        let moves: Vec<Move> = self.all_legal_moves().cloned().collect();
+        let mut nodes = 0;
+        for mov in moves {
+            self.make_move(&mov);
+            self.compute_legal_moves(true);
+            nodes += self.perft(depth - 1);
+            self.unmake_move();
+        }
+        // children overwrote the legal moves for this position
+        self.compute_legal_moves(true);
+        nodes
+    }
+    // perft split by root move, to find which move a generation bug is under
+    pub fn perft_divide(&mut self, depth: u8) -> Vec<(Move, u64)> {
+        let moves: Vec<Move> = self.all_legal_moves().cloned().collect();
+        let mut divide = vec![];
+        for mov in moves {
+            self.make_move(&mov);
+            self.compute_legal_moves(true);
+            let nodes = self.perft(depth.saturating_sub(1));
+            self.unmake_move();
+            divide.push((mov, nodes));
+        }
+        self.compute_legal_moves(true);
+        divide
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_counts(fen: &str, counts: &[u64]) {
+        let mut game = Game::from_fen(fen).unwrap();
+        for (depth, &nodes) in (1..).zip(counts) {
+            assert_eq!(game.perft(depth), nodes, "{} depth {}", fen, depth);
+        }
+    }
+
+    #[test]
+    fn initial_position() {
+        assert_counts(PERFT_SUITE[0].fen, &[20, 400, 8_902, 197_281]);
+    }
+
+    #[test]
+    fn kiwipete_shallow() {
+        assert_counts(PERFT_SUITE[1].fen, &[48, 2_039]);
+    }
+
+    #[test]
+    fn divide_adds_up() {
+        let mut game = Game::from_fen(PERFT_SUITE[0].fen).unwrap();
+        let divide = game.perft_divide(1);
+        assert_eq!(divide.len(), 20);
+        assert!(divide.iter().all(|(_, nodes)| *nodes == 1));
+        let divide = game.perft_divide(3);
+        assert_eq!(
+            divide.iter().map(|(_, nodes)| nodes).sum::<u64>(),
+            game.perft(3)
+        );
+        let nodes = |uci: &str| divide.iter().find(|(m, _)| m.to_uci() == uci).unwrap().1;
+        // the published split for the start position
+        assert_eq!(nodes("a2a3"), 380);
+        assert_eq!(nodes("g1f3"), 440);
+        // and the position is left as it was
+        assert_eq!(game.to_fen(), PERFT_SUITE[0].fen);
+        assert_eq!(game.legal_move_count(), 20);
+    }
+
+    // the first few published counts for each suite position, cheap enough to always run
+    #[test]
+    fn suite_shallow() {
+        let counts: [&[u64]; 5] = [
+            &[20, 400, 8_902],
+            &[48, 2_039],
+            &[14, 191, 2_812, 43_238],
+            &[6, 264, 9_467],
+            &[44, 1_486],
+        ];
+        for (position, counts) in PERFT_SUITE.iter().zip(counts) {
+            assert_counts(position.fen, counts);
+        }
+    }
+
+    // the full depths take minutes without optimizations, `cargo test --release -- --ignored`
+    #[test]
+    #[ignore]
+    fn suite_full_depth() {
+        for position in &PERFT_SUITE {
+            let mut game = Game::from_fen(position.fen).unwrap();
+            assert_eq!(
+                game.perft(position.depth),
+                position.nodes,
+                "{}",
+                position.name
+            );
+        }
+    }
+}
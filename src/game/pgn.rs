@@ -0,0 +1,292 @@
+use std::fmt::{Display, Formatter};
+
+use super::{Color, FenError, Game, GameResult};
+
+#[derive(Debug, PartialEq)]
+pub enum PgnError {
+    // a line starting with [ that isn't a [Name "value"] pair
+    BadTag(String),
+    // the FEN tag of a game that doesn't start from the standard position
+    BadFen(FenError),
+    // a SAN token that isn't a legal move, with the move number it was played on
+    IllegalMove(u16, String),
+}
+
+impl Display for PgnError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PgnError::BadTag(s) => write!(f, "invalid tag pair \"{}\"", s),
+            PgnError::BadFen(e) => write!(f, "invalid FEN tag: {}", e),
+            PgnError::IllegalMove(n, s) => write!(f, "illegal move \"{}\" on move {}", s, n),
+        }
+    }
+}
+
+// parses [Name "value"]
+fn parse_tag(line: &str) -> Option<(&str, &str)> {
+    let inner = line.strip_prefix('[')?.strip_suffix(']')?;
+    let (name, value) = inner.split_once(char::is_whitespace)?;
+    let value = value.trim().strip_prefix('"')?.strip_suffix('"')?;
+    Some((name, value))
+}
+
+// the movetext tokens of the mainline, without {comments}, ; comments to the end of the line
+// and (variations), which can nest. an unclosed comment or variation runs to the end
+fn mainline_tokens(movetext: &str) -> Vec<&str> {
+    let mut tokens = vec![];
+    // the byte the token being read started at
+    let mut start = None;
+    let mut in_comment = false;
+    let mut in_line_comment = false;
+    let mut variation_depth = 0usize;
+    for (i, c) in movetext.char_indices() {
+        let separator = c.is_whitespace() || "{};()".contains(c);
+        if separator {
+            if let Some(s) = start.take() {
+                tokens.push(&movetext[s..i]);
+            }
+        }
+        if in_comment {
+            in_comment = c != '}';
+        } else if in_line_comment {
+            in_line_comment = c != '\n';
+        } else {
+            match c {
+                '{' => in_comment = true,
+                ';' => in_line_comment = true,
+                '(' => variation_depth += 1,
+                ')' => variation_depth = variation_depth.saturating_sub(1),
+                _ if !separator && variation_depth == 0 && start.is_none() => start = Some(i),
+                _ => {}
+            }
+        }
+    }
+    if let Some(s) = start {
+        tokens.push(&movetext[s..]);
+    }
+    tokens
+}
+
+impl GameResult {
+    // result token used in the Result tag and at the end of the movetext
+    pub fn pgn_token(&self) -> &'static str {
+        match self {
+            GameResult::Ongoing => "*",
+            GameResult::Checkmate(Color::White) => "1-0",
+            GameResult::Checkmate(Color::Black) => "0-1",
+            GameResult::Resignation(Color::Black) => "1-0",
+            GameResult::Resignation(Color::White) => "0-1",
+            GameResult::Explosion(Color::White) => "1-0",
+            GameResult::Explosion(Color::Black) => "0-1",
+            GameResult::KingInCenter(Color::White) => "1-0",
+            GameResult::KingInCenter(Color::Black) => "0-1",
+            GameResult::ThreeCheckWin(Color::White) => "1-0",
+            GameResult::ThreeCheckWin(Color::Black) => "0-1",
+            _ => "1/2-1/2",
+        }
+    }
+}
+
+impl Game {
+    pub fn to_pgn(&self) -> String {
+        let result = self.result().pgn_token();
+
+        // SAN depends on the position before each move, so rewind a copy and replay it
+        let mut replay = self.clone();
+        while replay.unmake_move() {}
+        replay.compute_legal_moves(true);
+        let start_fen = replay.to_fen();
+
+        let mut pgn = String::new();
+        for (tag, value) in [
+            ("Event", "?"),
+            ("Site", "?"),
+            ("Date", "????.??.??"),
+            ("Round", "?"),
+            ("White", "?"),
+            ("Black", "?"),
+            ("Result", result),
+        ] {
+            pgn.push_str(&format!("[{} \"{}\"]\n", tag, value));
+        }
+        // only games that didn't start from the standard position need the FEN tags
+        if start_fen != Game::default().to_fen() {
+            pgn.push_str(&format!("[SetUp \"1\"]\n[FEN \"{}\"]\n", start_fen));
+        }
+        pgn.push('\n');
+
+        let mut tokens = vec![];
+        for (i, mov) in self.moves.iter().enumerate() {
+            if replay.turn == Color::White {
+                tokens.push(format!("{}.", replay.fullmove_number));
+            } else if i == 0 {
+                // game starting with black to move
+                tokens.push(format!("{}...", replay.fullmove_number));
+            }
+            tokens.push(replay.move_to_san(mov));
+            replay.make_move(mov);
+            replay.compute_legal_moves(true);
+        }
+        tokens.push(result.to_string());
+
+        // keep movetext lines under 80 characters
+        let mut line_length = 0;
+        for token in tokens {
+            if line_length > 0 {
+                if line_length + 1 + token.len() > 79 {
+                    pgn.push('\n');
+                    line_length = 0;
+                } else {
+                    pgn.push(' ');
+                    line_length += 1;
+                }
+            }
+            line_length += token.len();
+            pgn.push_str(&token);
+        }
+        pgn.push('\n');
+        pgn
+    }
+    pub fn from_pgn(pgn: &str) -> Result<Game, PgnError> {
+        let mut fen = None;
+        let mut movetext = String::new();
+        for line in pgn.lines() {
+            let line = line.trim();
+            // the tags come first, after that a [ is part of a comment
+            if movetext.trim().is_empty() && line.starts_with('[') {
+                let (name, value) =
+                    parse_tag(line).ok_or_else(|| PgnError::BadTag(line.to_string()))?;
+                // the other tags don't affect the game
+                if name == "FEN" {
+                    fen = Some(value.to_string());
+                }
+            } else {
+                movetext.push_str(line);
+                // ; comments end with the line
+                movetext.push('\n');
+            }
+        }
+
+        let mut game = match fen {
+            Some(fen) => Game::from_fen(&fen).map_err(PgnError::BadFen)?,
+            None => Game::default(),
+        };
+        for token in mainline_tokens(&movetext) {
+            // move numbers, either on their own or stuck to the move like 1.e4
+            let token = match token.rfind('.') {
+                Some(i) => &token[i + 1..],
+                None => token,
+            };
+            // numeric annotation glyphs like $1 carry no move
+            if token.is_empty() || token.starts_with('$') {
+                continue;
+            }
+            if ["1-0", "0-1", "1/2-1/2", "*"].contains(&token) {
+                break;
+            }
+            let mov = game
+                .parse_san(token)
+                .ok_or_else(|| PgnError::IllegalMove(game.fullmove_number, token.to_string()))?;
+            game.make_move(&mov);
+            game.compute_legal_moves(true);
+        }
+        Ok(game)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(game: &mut Game, moves: &[&str]) {
+        for san in moves {
+            let mov = game
+                .parse_san(san)
+                .unwrap_or_else(|| panic!("{} isn't legal", san));
+            assert!(game.request_move_promoting(&mov.from, &mov.target(), mov.promotion));
+        }
+    }
+
+    const SCHOLARS_MATE: [&str; 7] = ["e4", "e5", "Bc4", "Nc6", "Qh5", "Nf6", "Qxf7#"];
+
+    #[test]
+    fn scholars_mate_to_pgn() {
+        let mut game = Game::default();
+        play(&mut game, &SCHOLARS_MATE);
+        let pgn = game.to_pgn();
+        assert_eq!(
+            pgn,
+            "[Event \"?\"]\n[Site \"?\"]\n[Date \"????.??.??\"]\n[Round \"?\"]\n\
+             [White \"?\"]\n[Black \"?\"]\n[Result \"1-0\"]\n\n\
+             1. e4 e5 2. Bc4 Nc6 3. Qh5 Nf6 4. Qxf7# 1-0\n"
+        );
+        let parsed = Game::from_pgn(&pgn).unwrap();
+        assert_eq!(parsed.to_fen(), game.to_fen());
+        assert_eq!(parsed.result(), GameResult::Checkmate(Color::White));
+    }
+
+    #[test]
+    fn set_up_position_to_pgn() {
+        let fen = "4k3/8/8/8/8/8/7p/R3K3 b Q - 0 40";
+        let mut game = Game::from_fen(fen).unwrap();
+        play(&mut game, &["Kd7", "O-O-O+"]);
+        let pgn = game.to_pgn();
+        assert!(pgn.contains(&format!("[SetUp \"1\"]\n[FEN \"{}\"]\n", fen)));
+        assert!(pgn.ends_with("\n\n40... Kd7 41. O-O-O+ *\n"));
+    }
+
+    #[test]
+    fn round_trips() {
+        // morphy's opera game, castling, disambiguation, captures and mate
+        let opera = "e4 e5 Nf3 d6 d4 Bg4 dxe5 Bxf3 Qxf3 dxe5 Bc4 Nf6 Qb3 Qe7 Nc3 c6 Bg5 b5 \
+                     Nxb5 cxb5 Bxb5+ Nbd7 O-O-O Rd8 Rxd7 Rxd7 Rd1 Qe6 Bxd7+ Nxd7 Qb8+ Nxb8 Rd8#";
+        let promotion = "b8=Q+ Kd7 Qb5+ Kd8 h8=N";
+        for (fen, line) in [
+            (Game::default().to_fen(), opera),
+            ("4k3/1P5P/8/8/8/8/8/4K3 w - - 0 1".to_string(), promotion),
+        ] {
+            let mut game = Game::from_fen(&fen).unwrap();
+            play(&mut game, &line.split(' ').collect::<Vec<_>>());
+            let pgn = game.to_pgn();
+            let mut parsed = Game::from_pgn(&pgn).unwrap();
+            assert_eq!(parsed.moves, game.moves);
+            assert_eq!(parsed.to_fen(), game.to_fen());
+            assert_eq!(parsed.to_pgn(), pgn);
+            // the whole game is there to take back
+            while parsed.unmake_move() {}
+            assert_eq!(parsed.to_fen(), fen);
+        }
+    }
+
+    #[test]
+    fn comments_and_variations_are_skipped() {
+        let pgn = "[Event \"Casual\"]\n[Site \"?\"]\n[Annotator \"[bracketed]\"]\n\n\
+                   1.e4 {best by test} e5 2. Nf3 $1 (2. f4 exf4 (2... d5 {the \n\
+                   falkbeer} 3. exd5) 3. Nf3) 2... Nc6 ; the main line\n\
+                   3. Bb5 a6 (3... Nf6 4. O-O (4. d3) Nxe4) 4. Ba4 {[%clk 0:05:00]} 1/2-1/2\n";
+        let game = Game::from_pgn(pgn).unwrap();
+        let mut expected = Game::default();
+        play(
+            &mut expected,
+            &["e4", "e5", "Nf3", "Nc6", "Bb5", "a6", "Ba4"],
+        );
+        assert_eq!(game.moves, expected.moves);
+        assert_eq!(game.to_fen(), expected.to_fen());
+    }
+
+    #[test]
+    fn bad_pgn_is_an_error() {
+        assert_eq!(
+            Game::from_pgn("[Event Casual]\n\n1. e4").err(),
+            Some(PgnError::BadTag("[Event Casual]".to_string()))
+        );
+        assert_eq!(
+            Game::from_pgn("1. e4 e5 2. Ke3").err(),
+            Some(PgnError::IllegalMove(2, "Ke3".to_string()))
+        );
+        assert!(matches!(
+            Game::from_pgn("[FEN \"8/8 w - - 0 1\"]\n\n*"),
+            Err(PgnError::BadFen(_))
+        ));
+    }
+}
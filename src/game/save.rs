@@ -0,0 +1,103 @@
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+
+use super::Game;
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+}
+
+impl Display for SaveError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SaveError::Io(e) => write!(f, "couldn't access the save file: {}", e),
+            SaveError::Json(e) => write!(f, "invalid save file: {}", e),
+        }
+    }
+}
+
+impl From<std::io::Error> for SaveError {
+    fn from(e: std::io::Error) -> Self {
+        SaveError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for SaveError {
+    fn from(e: serde_json::Error) -> Self {
+        SaveError::Json(e)
+    }
+}
+
+impl Game {
+    pub fn to_json(&self) -> Result<String, SaveError> {
+        Ok(serde_json::to_string(self)?)
+    }
+    pub fn from_json(json: &str) -> Result<Game, SaveError> {
+        let mut game: Game = serde_json::from_str(json)?;
+        game.restore_skipped_state();
+        Ok(game)
+    }
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SaveError> {
+        Ok(std::fs::write(path, self.to_json()?)?)
+    }
+    pub fn load(path: impl AsRef<Path>) -> Result<Game, SaveError> {
+        Game::from_json(&std::fs::read_to_string(path)?)
+    }
+    // the hash, repetition counts and legal moves aren't saved, rebuild them from the moves
+    fn restore_skipped_state(&mut self) {
+        self.zobrist = self.compute_hash();
+        // rewind to the starting position and count every position on the way back
+        let moves = self.moves.clone();
+        while self.unmake_move() {}
+        self.position_counts.clear();
+        self.record_position();
+        for mov in &moves {
+            self.make_move(mov);
+        }
+        self.compute_legal_moves(true);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn played() -> Game {
+        let mut game = Game::default();
+        for text in ["e2e4", "g8f6", "g1f3", "f6g8", "f3g1"] {
+            let mov = game.parse_uci(text).unwrap();
+            assert!(game.request_move(&mov.from, &mov.target()));
+        }
+        game
+    }
+
+    #[test]
+    fn json_round_trips() {
+        let game = played();
+        let loaded = Game::from_json(&game.to_json().unwrap()).unwrap();
+        assert_eq!(loaded.to_fen(), game.to_fen());
+        assert_eq!(loaded.moves, game.moves);
+        // and everything that wasn't saved is rebuilt
+        assert_eq!(loaded.hash(), game.hash());
+        assert_eq!(loaded.repetitions(), 2);
+        assert_eq!(
+            loaded.all_legal_moves().collect::<Vec<_>>(),
+            game.all_legal_moves().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn save_and_load() {
+        let path =
+            std::env::temp_dir().join(format!("chess-save-test-{}.json", std::process::id()));
+        let game = played();
+        game.save(&path).unwrap();
+        let loaded = Game::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap().to_fen(), game.to_fen());
+        assert!(matches!(Game::load(&path), Err(SaveError::Io(_))));
+        assert!(matches!(Game::from_json("{}"), Err(SaveError::Json(_))));
+    }
+}
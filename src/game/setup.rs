@@ -0,0 +1,189 @@
+use std::fmt::{Display, Formatter};
+
+use super::{
+    square_to_algebraic, Board, CastlingRights, Color, Game, Piece, PieceType, Square, SquareExt,
+};
+
+#[derive(Debug, PartialEq)]
+pub enum SetupError {
+    // a square outside the board, as (row, col)
+    OffBoard(Square),
+    // the position needs exactly one king per side, with how many were given
+    KingCount(Color, usize),
+    // pawns can't stand on the first or last rank
+    PawnOnBackRank(Square),
+    // the side that just moved can't have left its own king in check
+    OpponentInCheck(Color),
+}
+
+impl Display for SetupError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SetupError::OffBoard((row, col)) => {
+                write!(f, "square ({}, {}) is off the board", row, col)
+            }
+            SetupError::KingCount(color, n) => {
+                write!(f, "{} needs exactly one king, found {}", color, n)
+            }
+            SetupError::PawnOnBackRank(square) => {
+                write!(
+                    f,
+                    "pawn on {} can't be on a back rank",
+                    square_to_algebraic(square)
+                )
+            }
+            SetupError::OpponentInCheck(color) => {
+                write!(f, "{} is in check but it's not their move", color)
+            }
+        }
+    }
+}
+
+impl Game {
+    // a position from a plain list of pieces, handy for puzzles and test positions.
+    // no castling rights, no en passant and fresh clocks. a square listed twice keeps the last piece
+    pub fn from_pieces(pieces: &[(Square, Piece)], turn: Color) -> Result<Game, SetupError> {
+        let mut board = Board::default();
+        for (square, piece) in pieces {
+            if square.to_indices().is_none() {
+                return Err(SetupError::OffBoard(*square));
+            }
+            board.set(square, Some(*piece));
+        }
+        check_board(&board)?;
+        let game = Game::from_position(
+            board,
+            turn,
+            CastlingRights {
+                white_queenside: false,
+                white_kingside: false,
+                black_queenside: false,
+                black_kingside: false,
+            },
+            Default::default(),
+            None,
+            0,
+            1,
+        );
+        game.check_opponent_not_in_check()?;
+        Ok(game)
+    }
+    // the side that just moved can't have left its king in check
+    pub(super) fn check_opponent_not_in_check(&self) -> Result<(), SetupError> {
+        let opponent = self.turn.invert();
+        if self.is_in_check(opponent) {
+            return Err(SetupError::OpponentInCheck(opponent));
+        }
+        Ok(())
+    }
+}
+
+// what a position needs before any moves can be generated for it. move generation assumes one
+// king a side, and a pawn on a back rank would step off the board
+pub(super) fn check_board(board: &Board) -> Result<(), SetupError> {
+    for color in [Color::White, Color::Black] {
+        let kings = board
+            .pieces()
+            .filter(|(_, p)| p.piece_type == PieceType::King && p.color == color)
+            .count();
+        if kings != 1 {
+            return Err(SetupError::KingCount(color, kings));
+        }
+    }
+    if let Some((square, _)) = board
+        .pieces()
+        .find(|((row, _), p)| p.piece_type == PieceType::Pawn && (*row == 0 || *row == 7))
+    {
+        return Err(SetupError::PawnOnBackRank(square));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::algebraic_to_square;
+    use super::*;
+
+    fn piece(name: &str, c: char) -> (Square, Piece) {
+        let color = if c.is_ascii_uppercase() {
+            Color::White
+        } else {
+            Color::Black
+        };
+        let piece_type = match c.to_ascii_lowercase() {
+            'k' => PieceType::King,
+            'q' => PieceType::Queen,
+            'r' => PieceType::Rook,
+            'p' => PieceType::Pawn,
+            _ => unreachable!(),
+        };
+        (
+            algebraic_to_square(name).unwrap(),
+            Piece { piece_type, color },
+        )
+    }
+
+    #[test]
+    fn puzzle_position() {
+        // back rank mate in one with Ra8
+        let pieces = [
+            piece("h1", 'K'),
+            piece("a1", 'R'),
+            piece("g8", 'k'),
+            piece("f7", 'p'),
+            piece("g7", 'p'),
+            piece("h7", 'p'),
+        ];
+        let game = Game::from_pieces(&pieces, Color::White).unwrap();
+        assert_eq!(game.to_fen(), "6k1/5ppp/8/8/8/8/8/R6K w - - 0 1");
+        // three king moves and thirteen rook moves
+        assert_eq!(game.legal_move_count(), 16);
+    }
+
+    #[test]
+    fn bad_positions() {
+        let kings = [piece("e1", 'K'), piece("e8", 'k')];
+        let with = |extra: &[(Square, Piece)]| {
+            let pieces: Vec<(Square, Piece)> = kings.iter().chain(extra).copied().collect();
+            Game::from_pieces(&pieces, Color::White).err()
+        };
+        assert_eq!(with(&[]), None);
+        assert_eq!(
+            Game::from_pieces(&[], Color::White).err(),
+            Some(SetupError::KingCount(Color::White, 0))
+        );
+        assert_eq!(
+            Game::from_pieces(&kings[..1], Color::White).err(),
+            Some(SetupError::KingCount(Color::Black, 0))
+        );
+        assert_eq!(
+            with(&[piece("a1", 'K')]),
+            Some(SetupError::KingCount(Color::White, 2))
+        );
+        assert_eq!(
+            with(&[piece("a8", 'k')]),
+            Some(SetupError::KingCount(Color::Black, 2))
+        );
+        for name in ["c1", "c8"] {
+            assert_eq!(
+                with(&[piece(name, 'p')]),
+                Some(SetupError::PawnOnBackRank(
+                    algebraic_to_square(name).unwrap()
+                ))
+            );
+        }
+        let (_, queen) = piece("a1", 'Q');
+        assert_eq!(with(&[((8, 0), queen)]), Some(SetupError::OffBoard((8, 0))));
+        assert_eq!(
+            with(&[((0, -1), queen)]),
+            Some(SetupError::OffBoard((0, -1)))
+        );
+        // white to move but black is already in check from the queen
+        assert_eq!(
+            with(&[piece("e4", 'Q')]),
+            Some(SetupError::OpponentInCheck(Color::Black))
+        );
+        // the side to move being in check is fine
+        assert_eq!(with(&[piece("e4", 'q')]), None);
+    }
+}
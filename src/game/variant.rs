@@ -0,0 +1,166 @@
+use serde::{Deserialize, Serialize};
+
+use super::{home_row, pawn_direction, Color, Game, GameResult, PieceType};
+
+// rule sets on top of the standard moves, set with set_variant before the first move
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum Variant {
+    #[default]
+    Standard,
+    // captures explode, taking out every piece but pawns around the target square
+    Atomic,
+    // a king reaching d4, d5, e4 or e5 wins on the spot
+    KingOfTheHill,
+    // the third check wins
+    ThreeCheck,
+}
+
+impl Variant {
+    // lowercase names for the command line, same order as the enum
+    pub const NAMES: [&'static str; 4] = ["standard", "atomic", "kingofthehill", "threecheck"];
+    pub fn from_name(name: &str) -> Option<Variant> {
+        match name {
+            "standard" => Some(Variant::Standard),
+            "atomic" => Some(Variant::Atomic),
+            "kingofthehill" => Some(Variant::KingOfTheHill),
+            "threecheck" => Some(Variant::ThreeCheck),
+            _ => None,
+        }
+    }
+    // the row pawns of this color can double move from, the one in front of the back row in
+    // every variant so far
+    pub fn pawn_start_row(self, color: Color) -> i8 {
+        match self {
+            Variant::Standard | Variant::Atomic | Variant::KingOfTheHill | Variant::ThreeCheck => {
+                home_row(color) + pawn_direction(color)
+            }
+        }
+    }
+}
+
+impl Game {
+    // only meant for games without moves yet, the ones played so far keep their old rules
+    pub fn set_variant(&mut self, variant: Variant) {
+        self.variant = variant;
+        self.compute_legal_moves(true);
+    }
+    // a win the variant's own rules give the side that just moved, checked before mate
+    pub fn variant_result(&self) -> Option<GameResult> {
+        let mover = self.turn.invert();
+        match self.variant {
+            Variant::Standard => None,
+            Variant::Atomic => self
+                .king_square(self.turn)
+                .is_none()
+                .then_some(GameResult::Explosion(mover)),
+            Variant::KingOfTheHill => self
+                .king_square(mover)
+                .is_some_and(|(row, col)| (3..=4).contains(&row) && (3..=4).contains(&col))
+                .then_some(GameResult::KingInCenter(mover)),
+            Variant::ThreeCheck => {
+                (self.checks(mover) >= 3).then_some(GameResult::ThreeCheckWin(mover))
+            }
+        }
+    }
+    // how many checks `color` has given so far, only counted in three check
+    pub fn checks(&self, color: Color) -> u8 {
+        self.checks_given[color as usize]
+    }
+    // not being able to mate is only a draw if the variant can't be won another way. a king can
+    // always walk to the hill, and anything but a king can give checks
+    pub(super) fn insufficient_material_draws(&self) -> bool {
+        match self.variant {
+            Variant::Standard | Variant::Atomic => true,
+            Variant::KingOfTheHill => false,
+            Variant::ThreeCheck => self
+                .board
+                .pieces()
+                .all(|(_, p)| p.piece_type == PieceType::King),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::algebraic_to_square;
+    use super::*;
+
+    fn variant_game(fen: &str, variant: Variant) -> Game {
+        let mut game = Game::from_fen(fen).unwrap();
+        game.set_variant(variant);
+        game
+    }
+
+    fn play(game: &mut Game, moves: &[&str]) {
+        for text in moves {
+            let mov = game.parse_uci(text).unwrap();
+            assert!(
+                game.request_move(&mov.from, &mov.to),
+                "{} isn't legal",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn king_of_the_hill() {
+        let mut game = variant_game("k7/8/8/8/8/8/8/4K3 w - - 0 1", Variant::KingOfTheHill);
+        // bare kings can still walk to the hill
+        assert_eq!(game.result(), GameResult::Ongoing);
+        play(&mut game, &["e1e2", "a8a7", "e2e3", "a7a8"]);
+        assert_eq!(game.result(), GameResult::Ongoing);
+        play(&mut game, &["e3e4"]);
+        assert_eq!(game.result(), GameResult::KingInCenter(Color::White));
+        assert!(!game.request_move(&(0, 0), &(1, 0)));
+        game.unmake_move_and_recalculate();
+        assert_eq!(game.result(), GameResult::Ongoing);
+    }
+
+    #[test]
+    fn three_checks_win() {
+        let mut game = variant_game("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", Variant::ThreeCheck);
+        // a rook can't mate alone but it can check
+        assert_eq!(game.result(), GameResult::Ongoing);
+        play(&mut game, &["a1a8", "e8e7", "a8a7", "e7e6"]);
+        assert_eq!(game.checks(Color::White), 2);
+        assert_eq!(game.result(), GameResult::Ongoing);
+        play(&mut game, &["a7a6"]);
+        assert_eq!(game.checks(Color::White), 3);
+        assert_eq!(game.checks(Color::Black), 0);
+        assert_eq!(game.result(), GameResult::ThreeCheckWin(Color::White));
+        game.unmake_move_and_recalculate();
+        assert_eq!(game.checks(Color::White), 2);
+        assert_eq!(game.result(), GameResult::Ongoing);
+        // nobody counts checks outside three check
+        let mut game = variant_game("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", Variant::Standard);
+        play(&mut game, &["a1a8"]);
+        assert_eq!(game.checks(Color::White), 0);
+    }
+
+    #[test]
+    fn double_moves_only_from_the_start_row() {
+        // horde puts white pawns on the third rank, they only get single steps from there
+        let game = Game::from_fen("4k3/8/4p3/8/8/4P3/3P4/4K3 w - - 0 1").unwrap();
+        let targets = |game: &Game, from: &str| -> Vec<String> {
+            let from = algebraic_to_square(from).unwrap();
+            game.legal_moves_on_square(from)
+                .iter()
+                .map(|m| m.to_uci()[2..].to_string())
+                .collect()
+        };
+        assert_eq!(targets(&game, "e3"), ["e4"]);
+        assert_eq!(targets(&game, "d2"), ["d3", "d4"]);
+        let game = Game::from_fen("4k3/3p4/4p3/8/8/4P3/8/4K3 b - - 0 1").unwrap();
+        assert_eq!(targets(&game, "e6"), ["e5"]);
+        assert_eq!(targets(&game, "d7"), ["d6", "d5"]);
+        for variant in [
+            Variant::Standard,
+            Variant::Atomic,
+            Variant::KingOfTheHill,
+            Variant::ThreeCheck,
+        ] {
+            assert_eq!(variant.pawn_start_row(Color::White), 6);
+            assert_eq!(variant.pawn_start_row(Color::Black), 1);
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use std::sync::OnceLock;
+
+use super::{CastlingRights, Color, Game, Piece, Square, SquareExt};
+
+pub struct ZobristKeys {
+    // [color][piece type][row * 8 + col]
+    pieces: [[[u64; 64]; 6]; 2],
+    // xored in when black is to move
+    black_to_move: u64,
+    // white kingside, white queenside, black kingside, black queenside
+    castling: [u64; 4],
+    // by file of the en passant target square
+    en_passant: [u64; 8],
+}
+
+// splitmix64, fixed seed so hashes are the same every run
+fn next_random(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e3779b97f4a7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+pub fn keys() -> &'static ZobristKeys {
+    static KEYS: OnceLock<ZobristKeys> = OnceLock::new();
+    KEYS.get_or_init(|| {
+        let mut state = 0x2545f4914f6cdd1d;
+        let mut keys = ZobristKeys {
+            pieces: [[[0; 64]; 6]; 2],
+            black_to_move: 0,
+            castling: [0; 4],
+            en_passant: [0; 8],
+        };
+        for key in keys.pieces.iter_mut().flatten().flatten() {
+            *key = next_random(&mut state);
+        }
+        keys.black_to_move = next_random(&mut state);
+        for key in keys.castling.iter_mut().chain(keys.en_passant.iter_mut()) {
+            *key = next_random(&mut state);
+        }
+        keys
+    })
+}
+
+impl ZobristKeys {
+    pub fn piece(&self, piece: &Piece, square: &Square) -> u64 {
+        // only called for squares on the board
+        let (row, col) = square.to_indices().expect("square is off the board");
+        self.pieces[piece.color as usize][piece.piece_type as usize][row * 8 + col]
+    }
+    pub fn turn(&self, turn: Color) -> u64 {
+        match turn {
+            Color::Black => self.black_to_move,
+            Color::White => 0,
+        }
+    }
+    pub fn castling(&self, rights: &CastlingRights) -> u64 {
+        [
+            rights.white_kingside,
+            rights.white_queenside,
+            rights.black_kingside,
+            rights.black_queenside,
+        ]
+        .iter()
+        .zip(self.castling)
+        .filter(|(right, _)| **right)
+        .fold(0, |hash, (_, key)| hash ^ key)
+    }
+    pub fn en_passant(&self, square: &Option<Square>) -> u64 {
+        match square {
+            Some((_, col)) => self.en_passant[*col as usize],
+            None => 0,
+        }
+    }
+}
+
+impl Game {
+    pub fn hash(&self) -> u64 {
+        self.zobrist
+    }
+    // full recompute, make_move and unmake_move keep it up to date incrementally after this
+    pub(super) fn compute_hash(&self) -> u64 {
+        let keys = keys();
+        let mut hash = keys.turn(self.turn)
+            ^ keys.castling(&self.castling_rights)
+            ^ keys.en_passant(&self.en_passant_target_square);
+        for (square, piece) in self.board.pieces() {
+            hash ^= keys.piece(&piece, &square);
+        }
+        hash
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Move, PERFT_SUITE};
+    use super::*;
+
+    #[test]
+    fn incremental_hash_matches_a_full_recompute() {
+        let mut state = 1;
+        for position in &PERFT_SUITE {
+            // a few random games from each, every move checked on the way down and back up
+            for _ in 0..5 {
+                let mut game = Game::from_fen(position.fen).unwrap();
+                let mut hashes = vec![game.hash()];
+                for _ in 0..40 {
+                    let moves: Vec<Move> = game.all_legal_moves().cloned().collect();
+                    if moves.is_empty() {
+                        break;
+                    }
+                    let mov = &moves[(next_random(&mut state) % moves.len() as u64) as usize];
+                    game.make_move(mov);
+                    game.compute_legal_moves(true);
+                    assert_eq!(game.hash(), game.compute_hash(), "after {}", mov.to_uci());
+                    hashes.push(game.hash());
+                }
+                while game.unmake_move() {
+                    hashes.pop();
+                    assert_eq!(Some(&game.hash()), hashes.last());
+                    assert_eq!(game.hash(), game.compute_hash());
+                }
+                assert_eq!(hashes.len(), 1);
+            }
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use macroquad::prelude::*;
+
+use crate::game::{is_valid_square, Square};
+
+// screen regions for one frame, recomputed from the window size so drawing and input agree
+pub struct BoardLayout {
+    // status and turn display across the top of the window
+    pub top_bar: Rect,
+    // move list, clocks and other side panels, may be zero width on narrow windows
+    pub right_panel: Rect,
+    // strips at the top and bottom of the panel for what the side shown at that edge captured
+    pub captured_top: Rect,
+    pub captured_bottom: Rect,
+    // the rest of the panel between the captured strips
+    pub move_list: Rect,
+    // the largest square that fits in the area left over
+    pub board: Rect,
+    pub square_size: f32,
+    // rotated 180 degrees, white's side at the top
+    pub flipped: bool,
+}
+
+impl BoardLayout {
+    pub fn new(width: f32, height: f32, flipped: bool) -> Self {
+        let top_bar = Rect::new(0f32, 0f32, width, height / 16f32);
+        let remaining_height = height - top_bar.h;
+        // the panel gets whatever horizontal space the board can't use, up to a third of the window
+        let panel_width = (width - remaining_height).clamp(0f32, width / 3f32);
+        let right_panel = Rect::new(
+            width - panel_width,
+            top_bar.h,
+            panel_width,
+            remaining_height,
+        );
+
+        let board_size = f32::min(width - panel_width, remaining_height);
+        let strip_height = board_size / 16f32;
+        let captured_top = Rect::new(right_panel.x, right_panel.y, panel_width, strip_height);
+        let captured_bottom = Rect::new(
+            right_panel.x,
+            right_panel.y + right_panel.h - strip_height,
+            panel_width,
+            strip_height,
+        );
+        let move_list = Rect::new(
+            right_panel.x,
+            right_panel.y + strip_height,
+            panel_width,
+            right_panel.h - 2f32 * strip_height,
+        );
+        let board = Rect::new(
+            (width - panel_width - board_size) / 2f32,
+            top_bar.h + (remaining_height - board_size) / 2f32,
+            board_size,
+            board_size,
+        );
+        BoardLayout {
+            top_bar,
+            right_panel,
+            captured_top,
+            captured_bottom,
+            move_list,
+            board,
+            square_size: board_size / 8f32,
+            flipped,
+        }
+    }
+    // board rows and columns to where they're shown on screen and back, it's its own inverse
+    fn orient(&self, (row, col): Square) -> Square {
+        if self.flipped {
+            (7 - row, 7 - col)
+        } else {
+            (row, col)
+        }
+    }
+    // top left corner of a square in screen coordinates
+    pub fn square_origin(&self, square: &Square) -> (f32, f32) {
+        let (row, col) = self.orient(*square);
+        (
+            self.board.x + col as f32 * self.square_size,
+            self.board.y + row as f32 * self.square_size,
+        )
+    }
+    // the board square under a screen position, None if it's outside the board
+    pub fn square_at(&self, (x, y): (f32, f32)) -> Option<Square> {
+        let row = ((y - self.board.y) / self.square_size).floor() as i8;
+        let col = ((x - self.board.x) / self.square_size).floor() as i8;
+        is_valid_square(&(row, col)).map(|s| self.orient(s))
+    }
+    // move list rows are sized off the board so the text scales with it
+    pub fn move_list_row_height(&self) -> f32 {
+        self.square_size / 2f32
+    }
+    // how many move list rows fit between the captured strips
+    pub fn move_list_rows(&self) -> usize {
+        (self.move_list.h / self.move_list_row_height()) as usize
+    }
+    // a cell of the move list, by visible row. column 0 is the move number, then white and black
+    pub fn move_list_cell(&self, row: usize, column: usize) -> Rect {
+        let number_width = self.move_list.w / 5f32;
+        let move_width = (self.move_list.w - number_width) / 2f32;
+        let row_height = self.move_list_row_height();
+        let (x, w) = match column {
+            0 => (self.move_list.x, number_width),
+            _ => (
+                self.move_list.x + number_width + (column - 1) as f32 * move_width,
+                move_width,
+            ),
+        };
+        Rect::new(x, self.move_list.y + row as f32 * row_height, w, row_height)
+    }
+    // the board editor's piece palette takes the move list's place, six to a row
+    pub fn palette_cell(&self, index: usize) -> Rect {
+        let size = self.move_list.w / 6f32;
+        Rect::new(
+            self.move_list.x + (index % 6) as f32 * size,
+            self.move_list.y + (index / 6) as f32 * size,
+            size,
+            size,
+        )
+    }
+    // a square of the small board the engine's line is played out on. it covers the top of the
+    // move list and is turned the same way as the main board
+    pub fn side_board_square(&self, square: &Square) -> Rect {
+        let size = self.move_list.w.min(self.move_list.h) / 8f32;
+        let (row, col) = self.orient(*square);
+        Rect::new(
+            self.move_list.x + col as f32 * size,
+            self.move_list.y + row as f32 * size,
+            size,
+            size,
+        )
+    }
+    // the claim draw button at the right end of the top bar, over the end of the status text
+    pub fn claim_draw_button(&self) -> Rect {
+        let margin = self.top_bar.h / 8f32;
+        let w = self.top_bar.h * 4f32;
+        Rect::new(
+            self.top_bar.x + self.top_bar.w - w - margin,
+            self.top_bar.y + margin,
+            w,
+            self.top_bar.h - 2f32 * margin,
+        )
+    }
+    // the square shown in the bottom left corner, where the rank and file labels meet
+    pub fn bottom_left(&self) -> Square {
+        self.orient((7, 0))
+    }
+}
@@ -0,0 +1,6 @@
+// the engine on its own, without the gui. main.rs is the macroquad front end built on top of it
+pub mod ai;
+pub mod epd;
+pub mod game;
+pub mod opening_book;
+pub mod uci;
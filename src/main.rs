@@ -1,31 +1,65 @@
-mod game;
+mod assets;
+mod clock;
+mod editor;
+mod layout;
+mod sounds;
+mod theme;
 
+use std::io::IsTerminal;
+use std::sync::mpsc::{self, TryRecvError};
+use std::thread;
+
+use chess::{ai, epd, game, uci};
 use glam::vec2;
 
-use crate::game::Color::White;
+use crate::layout::BoardLayout;
 use macroquad::prelude::*;
 
+// on the from and to squares of the move just played
+const LAST_MOVE: Color = color_u8!(205, 210, 106, 0x7f);
+// under the king of the side to move while it's in check
+const CHECK: Color = color_u8!(200, 30, 30, 0x7f);
+// on the from and to squares of a queued premove
+const PREMOVE: Color = color_u8!(20, 30, 85, 0x5f);
+// light layer over squares in the attacked squares overlay
+const ATTACKED: Color = color_u8!(230, 110, 40, 0x40);
+// the hint arrow
+const HINT: Color = color_u8!(30, 110, 200, 0xb0);
+// how much of its square's color is laid back over the "ghost" left on the origin square of a
+// dragged piece. a wash rather than a tint, so it looks the same whatever alpha the piece set has
+const GHOST_WASH: f32 = 0.7;
+// darkens the board behind the promotion choices and the game over banner
+const OVERLAY_SHADE: Color = color_u8!(0x00, 0x00, 0x00, 0x7f);
 
-const BACKGROUND: Color = color_u8!(0x16, 0x14, 0x12, 0xff);
-const LIGHT_SQUARE: Color = color_u8!(0xf0, 0xd9, 0xb5, 0xff);
-const DARK_SQUARE: Color = color_u8!(0xb5, 0x88, 0x63, 0xff);
-
-const SELECTED: Color = color_u8!(20, 85, 30, 0x7f);
+// in the order they're offered, nearest the promotion square first
+const PROMOTION_CHOICES: [game::PieceType; 4] = [
+    game::PieceType::Queen,
+    game::PieceType::Knight,
+    game::PieceType::Rook,
+    game::PieceType::Bishop,
+];
 
-const FONT: &[u8] = include_bytes!("../assets/Atkinson-Hyperlegible-Bold-102.ttf");
+// how long the computer thinks per move, on its own thread so the window keeps updating
+const AI_THINK_MILLIS: u64 = 1000;
+// hints are meant to be quick, a shallower search is plenty for a beginner
+const HINT_THINK_MILLIS: u64 = 200;
+// how long the search behind the engine's line gets, shown while P is held
+const LINE_THINK_MILLIS: u64 = 1000;
+// how long each position of the engine's line stays on the side board
+const LINE_STEP_SECONDS: f64 = 0.8;
+// the mistake finder searches every position of the game, so it can't look far
+const MISTAKE_DEPTH: u8 = 2;
+// anything that throws away less than this many centipawns isn't worth stopping on
+const MISTAKE_THRESHOLD: i32 = 150;
+// how long a typed move that couldn't be played stays in the status bar
+const MOVE_INPUT_ERROR_SECONDS: f64 = 2.0;
 
-const BLACK_BISHOP_FILE: &[u8] = include_bytes!("../assets/bB.png");
-const BLACK_KING_FILE: &[u8] = include_bytes!("../assets/bK.png");
-const BLACK_KNIGHT_FILE: &[u8] = include_bytes!("../assets/bN.png");
-const BLACK_PAWN_FILE: &[u8] = include_bytes!("../assets/bP.png");
-const BLACK_QUEEN_FILE: &[u8] = include_bytes!("../assets/bQ.png");
-const BLACK_ROOK_FILE: &[u8] = include_bytes!("../assets/bR.png");
-const WHITE_BISHOP_FILE: &[u8] = include_bytes!("../assets/wB.png");
-const WHITE_KING_FILE: &[u8] = include_bytes!("../assets/wK.png");
-const WHITE_KNIGHT_FILE: &[u8] = include_bytes!("../assets/wN.png");
-const WHITE_PAWN_FILE: &[u8] = include_bytes!("../assets/wP.png");
-const WHITE_QUEEN_FILE: &[u8] = include_bytes!("../assets/wQ.png");
-const WHITE_ROOK_FILE: &[u8] = include_bytes!("../assets/wR.png");
+// what the text box at the top is being typed into for
+#[derive(Clone, Copy, PartialEq)]
+enum TextInput {
+    Move,
+    Fen,
+}
 
 fn window_conf() -> Conf {
     Conf {
@@ -35,73 +69,436 @@ fn window_conf() -> Conf {
     }
 }
 
-#[macroquad::main(window_conf)]
-async fn main() {
+// `chess --perft-divide <depth> [fen]` prints the divide table instead of opening a window
+fn perft_divide(args: &[String]) {
+    let depth: u8 = match args.first().map(|d| d.parse()) {
+        Some(Ok(d)) => d,
+        _ => {
+            eprintln!("usage: chess --perft-divide <depth> [fen]");
+            return;
+        }
+    };
+    let mut game = if args.len() > 1 {
+        match game::Game::from_fen(&args[1..].join(" ")) {
+            Ok(g) => g,
+            Err(e) => {
+                eprintln!("invalid fen: {}", e);
+                return;
+            }
+        }
+    } else {
+        game::Game::default()
+    };
+    let mut total = 0;
+    for (mov, nodes) in game.perft_divide(depth) {
+        println!("{}: {}", mov.to_uci(), nodes);
+        total += nodes;
+    }
+    println!("\nNodes searched: {}", total);
+}
+
+// `chess --perft-suite` checks move generation against the known counts in game::PERFT_SUITE,
+// use --perft-divide on a failing position to narrow it down
+fn perft_suite() {
+    let mut failed = 0;
+    for position in &game::PERFT_SUITE {
+        let mut game = game::Game::from_fen(position.fen).expect("suite fens are valid");
+        let nodes = game.perft(position.depth);
+        let ok = nodes == position.nodes;
+        println!(
+            "{} perft({}): {} {} (expected {})",
+            position.name,
+            position.depth,
+            if ok { "ok" } else { "FAIL" },
+            nodes,
+            position.nodes
+        );
+        failed += !ok as usize;
+    }
+    println!("\n{} of {} failed", failed, game::PERFT_SUITE.len());
+}
+
+// `chess --epd <depth> [file]` searches every position in an epd file and reports which best
+// moves it found, the built in mate in two set without a file
+fn epd_suite(args: &[String]) {
+    let depth: u8 = match args.first().map(|d| d.parse()) {
+        Some(Ok(d)) => d,
+        _ => {
+            eprintln!("usage: chess --epd <depth> [file]");
+            return;
+        }
+    };
+    let contents = match args.get(1) {
+        Some(path) => match std::fs::read_to_string(path) {
+            Ok(c) => c,
+            Err(e) => {
+                eprintln!("couldn't read {}: {}", path, e);
+                return;
+            }
+        },
+        None => epd::MATE_IN_TWO.join("\n"),
+    };
+    let (mut solved, mut total) = (0, 0);
+    for (i, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut position = match epd::EpdPosition::parse(line.trim()) {
+            Ok(p) => p,
+            Err(e) => {
+                eprintln!("line {}: {}", i + 1, e);
+                continue;
+            }
+        };
+        let (found, ok) = position.solve(depth);
+        let san = |mov: &game::Move| position.game.move_to_san(mov);
+        println!(
+            "{}: {} {} (best {})",
+            position
+                .id
+                .clone()
+                .unwrap_or_else(|| format!("line {}", i + 1)),
+            if ok { "ok" } else { "FAIL" },
+            found.as_ref().map_or("none".to_string(), san),
+            position
+                .best_moves
+                .iter()
+                .map(san)
+                .collect::<Vec<_>>()
+                .join(" ")
+        );
+        solved += ok as usize;
+        total += 1;
+    }
+    println!("\nSolved {} of {}", solved, total);
+}
+
+// `chess --cli` plays in the terminal instead of a window, one move per line on stdin in
+// coordinate notation or SAN, until the game ends or stdin does
+fn cli() {
+    let mut game = game::Game::default();
+    let mut lines = std::io::stdin().lines();
+    // the colored board shows empty squares by their background, which is lost when piped
+    let board = |game: &game::Game| {
+        if std::io::stdout().is_terminal() {
+            format!("{:#}", game)
+        } else {
+            game.to_ascii(true)
+        }
+    };
+    println!("{}", board(&game));
+    loop {
+        let result = game.result();
+        if result != game::GameResult::Ongoing {
+            println!("{}", result);
+            return;
+        }
+        print!("{} to play: ", game.turn);
+        // the prompt has no newline, it only shows up once flushed
+        let _ = std::io::Write::flush(&mut std::io::stdout());
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            // end of input, or input that isn't text
+            _ => {
+                println!();
+                return;
+            }
+        };
+        let text = line.trim();
+        if text.is_empty() {
+            continue;
+        }
+        let mov = game.parse_uci(text).or_else(|| game.parse_san(text));
+        let san = mov.as_ref().map(|m| game.move_to_san(m));
+        match (mov, san) {
+            (Some(m), Some(san))
+                if game.request_move_promoting(&m.from, &m.target(), m.promotion) =>
+            {
+                println!("{}\n{}", san, board(&game));
+            }
+            _ => println!("illegal move \"{}\"", text),
+        }
+    }
+}
+
+// the promotion choices stack from the promotion square towards the middle of the board, so
+// down for white and up for black
+fn promotion_choice_squares(to: &game::Square) -> [game::Square; 4] {
+    let direction = if to.0 == 0 { 1 } else { -1 };
+    [0, 1, 2, 3].map(|i| (to.0 + direction * i, to.1))
+}
+
+// a straight arrow between two screen points, `width` thick with the head ending on `to`
+fn draw_arrow(from: Vec2, to: Vec2, width: f32, color: Color) {
+    let direction = (to - from).normalize_or_zero();
+    let head_length = width * 3f32;
+    let head_base = to - direction * head_length;
+    let side = direction.perp() * head_length / 2f32;
+    draw_line(from.x, from.y, head_base.x, head_base.y, width, color);
+    draw_triangle(to, head_base + side, head_base - side, color);
+}
+
+// plays the move unless it promotes, then it's handed back to wait for the player's choice.
+// with auto_queen promotions are played as a queen straight away instead
+fn request_move_or_promotion(
+    game: &mut game::Game,
+    from: &game::Square,
+    to: &game::Square,
+    auto_queen: bool,
+) -> Option<(game::Square, game::Square)> {
+    if !auto_queen
+        && game
+            .legal_moves_on_square(*from)
+            .iter()
+            .any(|m| m.to == *to && m.promotion.is_some())
+    {
+        Some((*from, *to))
+    } else {
+        game.request_move(from, to);
+        None
+    }
+}
+
+// searches a copy of the position on its own thread so the window keeps updating. the hash of
+// the position goes along with the receiver so a reply to any other position can be thrown away
+fn search_in_background(
+    game: &game::Game,
+    millis: u64,
+) -> (mpsc::Receiver<Option<game::Move>>, u64) {
+    let (sender, receiver) = mpsc::channel();
+    let mut position = game.clone();
+    thread::spawn(move || {
+        let _ = sender.send(ai::best_move_timed(&mut position, millis));
+    });
+    (receiver, game.hash())
+}
+
+// the search's answer once it has one. a search that died without answering is treated like one
+// with no move
+fn search_reply(receiver: &mpsc::Receiver<Option<game::Move>>) -> Option<Option<game::Move>> {
+    match receiver.try_recv() {
+        Ok(reply) => Some(reply),
+        Err(TryRecvError::Empty) => None,
+        Err(TryRecvError::Disconnected) => Some(None),
+    }
+}
+
+// the engine's line from a copy of the position, found on its own thread like
+// search_in_background
+fn best_line_in_background(
+    game: &game::Game,
+    millis: u64,
+) -> (mpsc::Receiver<Vec<game::Move>>, u64) {
+    let (sender, receiver) = mpsc::channel();
+    let mut position = game.clone();
+    thread::spawn(move || {
+        let _ = sender.send(ai::best_line_timed(&mut position, millis));
+    });
+    (receiver, game.hash())
+}
+
+// the mistakes in a copy of the game, found on its own thread like search_in_background. the
+// hash is of the game's last position
+fn find_mistakes_in_background(game: &game::Game) -> (mpsc::Receiver<Vec<ai::Mistake>>, u64) {
+    let (sender, receiver) = mpsc::channel();
+    let position = game.clone();
+    thread::spawn(move || {
+        let _ = sender.send(ai::find_mistakes(
+            &position,
+            MISTAKE_DEPTH,
+            MISTAKE_THRESHOLD,
+        ));
+    });
+    (receiver, game.hash())
+}
+
+// steps the reviewed copy to ply, handing the live game back once it gets there
+fn review_to_ply(game: &mut game::Game, review: &mut Option<game::Game>, ply: usize) {
+    if review.as_ref().is_some_and(|live| ply >= live.moves.len()) {
+        if let Some(live) = review.take() {
+            *game = live;
+        }
+    } else {
+        game.go_to_ply(ply);
+    }
+}
+
+// the fullmove number of the game's first move and whether black played it, for the move list
+fn first_move(game: &game::Game) -> (u16, bool) {
+    let played = game.moves.len();
+    // the side to move flips every ply
+    let black_first = (game.turn == game::Color::Black) != (played % 2 == 1);
+    // the fullmove number goes up after each of black's moves
+    let black_moves = (played + black_first as usize) / 2;
+    (game.fullmove_number - black_moves as u16, black_first)
+}
+
+// the pieces `color` has taken so far, grouped by type with the pawns first
+fn captured_by(game: &game::Game, color: game::Color) -> Vec<game::PieceType> {
+    let (_, black_first) = first_move(game);
+    let mut captured: Vec<game::PieceType> = game
+        .moves
+        .iter()
+        .enumerate()
+        .filter(|(ply, _)| {
+            let mover = if ply.is_multiple_of(2) != black_first {
+                game::Color::White
+            } else {
+                game::Color::Black
+            };
+            mover == color
+        })
+        .filter_map(|(_, mov)| mov.capture)
+        .collect();
+    captured.sort_by_key(|piece_type| *piece_type as u8);
+    captured
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("--perft-divide") {
+        perft_divide(&args[2..]);
+        return;
+    }
+    if args.get(1).map(String::as_str) == Some("--perft-suite") {
+        perft_suite();
+        return;
+    }
+    if args.get(1).map(String::as_str) == Some("--cli") {
+        cli();
+        return;
+    }
+    if args.get(1).map(String::as_str) == Some("--uci") {
+        if let Err(e) = uci::run(std::io::stdin().lock(), std::io::stdout()) {
+            eprintln!("uci: {}", e);
+        }
+        return;
+    }
+    if args.get(1).map(String::as_str) == Some("--epd") {
+        epd_suite(&args[2..]);
+        return;
+    }
+    // `--clock <minutes>+<increment>` plays with clocks, without it there's no time limit
+    let mut clocks = None;
+    let mut variant = game::Variant::Standard;
+    let mut options = args[1..].iter();
+    while let Some(option) = options.next() {
+        let value = options.next().map(String::as_str);
+        let parsed = match (option.as_str(), value) {
+            ("--clock", Some(v)) => clock::Clocks::parse(v).map(|c| clocks = Some(c)),
+            ("--variant", Some(v)) => game::Variant::from_name(v).map(|v| variant = v),
+            _ => None,
+        };
+        if parsed.is_none() {
+            eprintln!(
+                "usage: chess [--clock <minutes>+<increment seconds>] [--variant {}]",
+                game::Variant::NAMES.join("|")
+            );
+            return;
+        }
+    }
+    macroquad::Window::from_config(window_conf(), run(clocks, variant));
+}
+
+// undo and redo don't give back the time spent, the clocks only ever run forward
+async fn run(mut clocks: Option<clock::Clocks>, variant: game::Variant) {
     clear_background(WHITE);
-    let font = load_ttf_font_from_bytes(FONT).unwrap();
-    draw_text_ex(
+    draw_text(
         "Loading...",
         screen_width() / 2f32,
         screen_height() / 2f32,
-        TextParams {
-            font_size: 32,
-            color: BLACK,
-            font: Some(&font),
-            ..Default::default()
-        },
+        32f32,
+        BLACK,
     );
     next_frame().await;
 
-    let black_bishop: Texture2D =
-        Texture2D::from_file_with_format(BLACK_BISHOP_FILE, Some(ImageFormat::Png));
-    let black_king: Texture2D =
-        Texture2D::from_file_with_format(BLACK_KING_FILE, Some(ImageFormat::Png));
-    let black_knight: Texture2D =
-        Texture2D::from_file_with_format(BLACK_KNIGHT_FILE, Some(ImageFormat::Png));
-    let black_pawn: Texture2D =
-        Texture2D::from_file_with_format(BLACK_PAWN_FILE, Some(ImageFormat::Png));
-    let black_queen: Texture2D =
-        Texture2D::from_file_with_format(BLACK_QUEEN_FILE, Some(ImageFormat::Png));
-    let black_rook: Texture2D =
-        Texture2D::from_file_with_format(BLACK_ROOK_FILE, Some(ImageFormat::Png));
-    let white_bishop: Texture2D =
-        Texture2D::from_file_with_format(WHITE_BISHOP_FILE, Some(ImageFormat::Png));
-    let white_king: Texture2D =
-        Texture2D::from_file_with_format(WHITE_KING_FILE, Some(ImageFormat::Png));
-    let white_knight: Texture2D =
-        Texture2D::from_file_with_format(WHITE_KNIGHT_FILE, Some(ImageFormat::Png));
-    let white_pawn: Texture2D =
-        Texture2D::from_file_with_format(WHITE_PAWN_FILE, Some(ImageFormat::Png));
-    let white_queen: Texture2D =
-        Texture2D::from_file_with_format(WHITE_QUEEN_FILE, Some(ImageFormat::Png));
-    let white_rook: Texture2D =
-        Texture2D::from_file_with_format(WHITE_ROOK_FILE, Some(ImageFormat::Png));
+    let assets = match assets::load_assets() {
+        Ok(a) => a,
+        Err(e) => {
+            eprintln!("{}", e);
+            // keep the error on screen instead of vanishing mid-startup
+            loop {
+                clear_background(WHITE);
+                draw_text(&e.to_string(), 10f32, 32f32, 24f32, BLACK);
+                next_frame().await;
+            }
+        }
+    };
+    let font = &assets.font;
+    let audio = sounds::Sounds::load().await;
 
     let mut game = game::Game::default();
+    game.set_variant(variant);
+    // what a new game started with N gets
+    let starting_clocks = clocks.clone();
 
     let mut moving_piece: Option<game::Square> = None;
     let mut selected_piece: Option<game::Square> = None;
+    // the computer plays black when this is on, toggled with C
+    let mut computer_opponent = false;
+    // tints the squares the side to move attacks, toggled with X
+    let mut show_attacked = false;
+    // the attacked squares overlay shows the opponent's attacks instead, toggled with V
+    let mut show_opponent_attacks = false;
+    // index into theme::THEMES, cycled with T
+    let mut theme_index = 0;
+    // black's side at the bottom, toggled with F
+    let mut flipped = false;
+    // keeps the side to move at the bottom instead, toggled with A
+    let mut auto_flip = false;
+    // promotes to a queen without asking, toggled with Q
+    let mut auto_queen = false;
+    // a promoting move waiting for the player to pick a piece
+    let mut pending_promotion: Option<(game::Square, game::Square)> = None;
+    // the engine's suggestion, with the hash of the position it's for so it disappears after
+    // any move or undo. asked for with H
+    let mut hint: Option<(game::Move, u64)> = None;
+    // a move picked while the computer is to move, tried as soon as it has replied
+    let mut premove: Option<(game::Square, game::Square)> = None;
+    // how many moves had been played last frame, to notice new ones
+    let mut played_moves = game.moves.len();
+    // the line in the move list and its SAN, only rebuilt when the line changes
+    let mut move_list_line: Vec<game::Move> = vec![];
+    let mut move_list_san: Vec<String> = vec![];
+    // first move list row on screen, follows the current move and the mouse wheel
+    let mut move_list_scroll = 0usize;
+    // the current move last frame, to scroll to it when it changes
+    let mut move_list_ply = game.moves.len();
+    // the side to move when the last frame started, it's charged for that frame
+    let mut clock_turn = game.turn;
+    // a position being set up by hand, toggled with E. the game is paused meanwhile
+    let mut editor: Option<editor::Editor> = None;
+    let palette = editor::palette();
+    // the game as it really stands while Left and Right step through its moves. the board is
+    // read only until Right gets back to it
+    let mut review: Option<game::Game> = None;
+    // the computer's search running on its own thread, see search_in_background
+    let mut thinking: Option<(mpsc::Receiver<Option<game::Move>>, u64)> = None;
+    // the same for a hint that's been asked for and isn't ready yet
+    let mut hinting: Option<(mpsc::Receiver<Option<game::Move>>, u64)> = None;
+    // the search behind the engine's line, started by holding P
+    let mut finding_line: Option<(mpsc::Receiver<Vec<game::Move>>, u64)> = None;
+    // the positions along the engine's line, each with the SAN of the move into it, and the hash
+    // of the position it starts from. they're all copies, the game itself is never touched
+    let mut engine_line: Option<(Vec<(String, game::Game)>, u64)> = None;
+    // when the engine's line started playing out
+    let mut engine_line_start = 0f64;
+    // the search for mistakes in a finished game, asked for with B
+    let mut finding_mistakes: Option<(mpsc::Receiver<Vec<ai::Mistake>>, u64)> = None;
+    // the mistakes it found, the one on the board and the hash of the game's last position.
+    // Left and Right step between them instead of between moves until Esc
+    let mut mistakes: Option<(Vec<ai::Mistake>, usize, u64)> = None;
+    // text being typed and what it's for, a move opened with Enter or a fen opened with L. the
+    // letter shortcuts are off meanwhile
+    let mut text_input: Option<(TextInput, String)> = None;
+    // the last typed move that couldn't be played and when, flashed in the status bar
+    let mut move_input_error: Option<(String, f64)> = None;
+    // why the typed fen couldn't be loaded, shown until the box is closed or typed in again
+    let mut fen_input_error: Option<String> = None;
 
     let draw_piece = |p: &game::Piece, x: f32, y: f32, size: f32, color: Color| {
         draw_texture_ex(
-            match p.color {
-                game::Color::Black => match p.piece_type {
-                    game::PieceType::Pawn => &black_pawn,
-                    game::PieceType::Knight => &black_knight,
-                    game::PieceType::Bishop => &black_bishop,
-                    game::PieceType::Rook => &black_rook,
-                    game::PieceType::Queen => &black_queen,
-                    game::PieceType::King => &black_king,
-                },
-                White => match p.piece_type {
-                    game::PieceType::Pawn => &white_pawn,
-                    game::PieceType::Knight => &white_knight,
-                    game::PieceType::Bishop => &white_bishop,
-                    game::PieceType::Rook => &white_rook,
-                    game::PieceType::Queen => &white_queen,
-                    game::PieceType::King => &white_king,
-                },
-            },
+            &assets.pieces[&(p.color, p.piece_type)],
             x,
             y,
             color,
@@ -112,38 +509,504 @@ async fn main() {
         );
     };
     loop {
-        clear_background(BACKGROUND);
-        let width = screen_width();
-        let height = screen_height();
-        let board_size = f32::min(width, height);
-        let top_left = ((width - board_size) / 2f32, (height - board_size) / 2f32);
-        let square_size = board_size / 8f32;
+        let typing = text_input.is_some();
+        if let Some((kind, text)) = &mut text_input {
+            let kind = *kind;
+            while let Some(c) = get_char_pressed() {
+                // fens have spaces between their fields, moves never do
+                if c.is_ascii_graphic() || (kind == TextInput::Fen && c == ' ') {
+                    text.push(c);
+                    fen_input_error = None;
+                }
+            }
+            if is_key_pressed(KeyCode::Backspace) {
+                text.pop();
+                fen_input_error = None;
+            }
+            if is_key_pressed(KeyCode::Escape) {
+                text_input = None;
+                fen_input_error = None;
+            } else if is_key_pressed(KeyCode::Enter) && kind == TextInput::Move {
+                let text = text_input.take().map(|(_, t)| t).unwrap_or_default();
+                let mov = game
+                    .parse_uci(text.trim())
+                    .or_else(|| game.parse_san(text.trim()));
+                // same as clicking, only on the player's own turn in a game that's still going
+                let can_move = !(computer_opponent && game.turn == game::Color::Black)
+                    && clocks.as_ref().and_then(|c| c.flagged()).is_none()
+                    && review.is_none();
+                let played = can_move
+                    && mov.is_some_and(|m| {
+                        game.request_move_promoting(&m.from, &m.target(), m.promotion)
+                    });
+                if played {
+                    moving_piece = None;
+                    selected_piece = None;
+                    pending_promotion = None;
+                    premove = None;
+                } else if !text.trim().is_empty() {
+                    move_input_error = Some((text, get_time()));
+                }
+            } else if is_key_pressed(KeyCode::Enter) {
+                // a fen that doesn't parse leaves the box open with the error and the game as it was
+                match game::Game::from_fen(text.trim()) {
+                    Ok(mut g) => {
+                        g.set_variant(game.variant);
+                        // a new game like N starts, only from the typed position
+                        game = g;
+                        clocks = starting_clocks.clone();
+                        text_input = None;
+                        moving_piece = None;
+                        selected_piece = None;
+                        pending_promotion = None;
+                        premove = None;
+                        hint = None;
+                    }
+                    Err(e) => fen_input_error = Some(e.to_string()),
+                }
+            }
+        } else {
+            // anything typed outside the box shouldn't show up in it later
+            while get_char_pressed().is_some() {}
+            if editor.is_none() && review.is_none() {
+                if is_key_pressed(KeyCode::Enter) {
+                    text_input = Some((TextInput::Move, String::new()));
+                } else if is_key_pressed(KeyCode::L) {
+                    text_input = Some((TextInput::Fen, String::new()));
+                }
+            }
+        }
+        let shortcut = |key: KeyCode| !typing && is_key_pressed(key);
+        if shortcut(KeyCode::T) {
+            theme_index = (theme_index + 1) % theme::THEMES.len();
+        }
+        let theme = &theme::THEMES[theme_index];
+        clear_background(theme.background);
+        // nothing runs before the first move, like most online clocks. reviewing doesn't stop
+        // the real game's clock
+        let live = review.as_ref().unwrap_or(&game);
+        if let Some(clocks) = &mut clocks {
+            if !live.moves.is_empty()
+                && live.result() == game::GameResult::Ongoing
+                && editor.is_none()
+            {
+                clocks.tick(clock_turn, get_frame_time());
+            }
+        }
+        clock_turn = live.turn;
+        let flagged = clocks.as_ref().and_then(|c| c.flagged());
+        // moves played back by redo or the move list don't earn an increment
+        let mut replayed = false;
+        if shortcut(KeyCode::F) {
+            flipped = !flipped;
+        }
+        if shortcut(KeyCode::A) {
+            auto_flip = !auto_flip;
+        }
+        let layout = BoardLayout::new(
+            screen_width(),
+            screen_height(),
+            if auto_flip {
+                game.turn == game::Color::Black
+            } else {
+                flipped
+            },
+        );
+        let (bottom_row, left_col) = layout.bottom_left();
+        let square_size = layout.square_size;
 
         let mouse_pos = mouse_position();
-        let row = ((mouse_pos.1 - top_left.1) / square_size).floor() as i8;
-        let col = ((mouse_pos.0 - top_left.0) / square_size).floor() as i8;
-        let mouse_square_option = game::is_valid_square(&(row, col));
+        let mouse_square_option = layout.square_at(mouse_pos);
 
-        if is_key_pressed(KeyCode::Z) {
+        if shortcut(KeyCode::X) {
+            show_attacked = !show_attacked;
+        }
+        if shortcut(KeyCode::V) {
+            show_opponent_attacks = !show_opponent_attacks;
+        }
+        if review.is_none() && shortcut(KeyCode::E) {
+            // leaving without starting throws the edits away
+            editor = match editor {
+                Some(_) => None,
+                None => Some(editor::Editor::new(&game)),
+            };
+            moving_piece = None;
+            selected_piece = None;
+            pending_promotion = None;
+            premove = None;
+        }
+        let editing = editor.is_some();
+        // the game the mistakes were found in is gone, after a new game or an undo
+        let live_hash = live.hash();
+        if finding_mistakes
+            .as_ref()
+            .is_some_and(|(_, hash)| *hash != live_hash)
+        {
+            finding_mistakes = None;
+        }
+        if mistakes
+            .as_ref()
+            .is_some_and(|(_, _, hash)| *hash != live_hash || editing)
+            || shortcut(KeyCode::Escape)
+        {
+            mistakes = None;
+        }
+        let finished = flagged.is_some() || live.result() != game::GameResult::Ongoing;
+        if !editing
+            && finished
+            && finding_mistakes.is_none()
+            && mistakes.is_none()
+            && shortcut(KeyCode::B)
+        {
+            finding_mistakes = Some(find_mistakes_in_background(live));
+        }
+        let mut to_mistake = None;
+        if let Some((receiver, hash)) = &finding_mistakes {
+            match receiver.try_recv() {
+                Ok(found) => {
+                    to_mistake = found.first().map(|m| m.ply);
+                    mistakes = Some((found, 0, *hash));
+                    finding_mistakes = None;
+                }
+                Err(TryRecvError::Empty) => {}
+                Err(TryRecvError::Disconnected) => finding_mistakes = None,
+            }
+        }
+        let stepping_mistakes = mistakes
+            .as_ref()
+            .is_some_and(|(found, _, _)| !found.is_empty());
+        if let Some((found, index, _)) = mistakes.as_mut() {
+            let step = if shortcut(KeyCode::Left) {
+                index.checked_sub(1)
+            } else if shortcut(KeyCode::Right) {
+                Some(*index + 1).filter(|i| *i < found.len())
+            } else {
+                None
+            };
+            if let Some(step) = step {
+                *index = step;
+                to_mistake = Some(found[step].ply);
+            }
+        }
+        // the position just before the mistake, with the live game kept aside like Left does
+        if let Some(ply) = to_mistake {
+            if review.is_none() {
+                review = Some(game.clone());
+            }
+            review_to_ply(&mut game, &mut review, ply);
+            moving_piece = None;
+            selected_piece = None;
+            pending_promotion = None;
+            premove = None;
+        }
+        if !editing && !stepping_mistakes && shortcut(KeyCode::Left) && !game.moves.is_empty() {
+            if review.is_none() {
+                review = Some(game.clone());
+            }
+            game.go_to_ply(game.moves.len() - 1);
+            moving_piece = None;
+            selected_piece = None;
+            pending_promotion = None;
+            premove = None;
+        } else if !stepping_mistakes && shortcut(KeyCode::Right) && review.is_some() {
+            let next = game.moves.len() + 1;
+            review_to_ply(&mut game, &mut review, next);
+            replayed = true;
+        }
+        if shortcut(KeyCode::C) {
+            computer_opponent = !computer_opponent;
+            premove = None;
+        }
+        if shortcut(KeyCode::Q) {
+            auto_queen = !auto_queen;
+        }
+        let shift = is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift);
+        // resigning and draw offers are for whoever is at the keyboard, the player to move or
+        // always white against the computer, which never takes a draw
+        let player = if computer_opponent {
+            game::Color::White
+        } else {
+            game.turn
+        };
+        if !editing && shortcut(KeyCode::N) {
+            game.reset();
+            clocks = starting_clocks.clone();
+            review = None;
+            moving_piece = None;
+            selected_piece = None;
+            pending_promotion = None;
+            premove = None;
+            hint = None;
+        }
+        let reviewing = review.is_some();
+        if flagged.is_none() && !editing && !reviewing {
+            if shortcut(KeyCode::R) {
+                game.resign(player);
+            }
+            if shortcut(KeyCode::D) && !game.accept_draw(player) {
+                game.offer_draw(player);
+            }
+        }
+        if !editing && !reviewing && (shortcut(KeyCode::Y) || (shift && shortcut(KeyCode::Z))) {
+            pending_promotion = None;
+            premove = None;
+            replayed = true;
+            game.redo_move_and_recalculate();
+            // undo took back the computer's reply too, so put both back
+            if computer_opponent && game.turn == game::Color::Black {
+                game.redo_move_and_recalculate();
+            }
+        } else if !editing && !reviewing && shortcut(KeyCode::Z) {
+            pending_promotion = None;
+            premove = None;
             game.unmake_move_and_recalculate();
+            // take back the computer's reply too, otherwise it just plays again
+            if computer_opponent && game.turn == game::Color::Black {
+                game.unmake_move_and_recalculate();
+            }
+        }
+        // clicking a move in the list jumps to the position after it, undone moves included
+        let (first_number, black_first) = first_move(&game);
+        let mouse_vec = vec2(mouse_pos.0, mouse_pos.1);
+        if !editing && layout.move_list.contains(mouse_vec) {
+            if is_mouse_button_pressed(MouseButton::Left) {
+                let clicked = (0..move_list_san.len()).find(|ply| {
+                    let slot = ply + black_first as usize;
+                    slot / 2 >= move_list_scroll
+                        && layout
+                            .move_list_cell(slot / 2 - move_list_scroll, 1 + slot % 2)
+                            .contains(mouse_vec)
+                });
+                if let Some(ply) = clicked {
+                    pending_promotion = None;
+                    premove = None;
+                    replayed = true;
+                    if reviewing {
+                        review_to_ply(&mut game, &mut review, ply + 1);
+                    } else {
+                        game.go_to_ply(ply + 1);
+                    }
+                }
+            }
+            let wheel = mouse_wheel().1;
+            if wheel > 0f32 {
+                move_list_scroll = move_list_scroll.saturating_sub(1);
+            } else if wheel < 0f32 {
+                move_list_scroll += 1;
+            }
+        }
+        // threefold repetition and the fifty move rule only end the game when someone claims them
+        let draw_claim = game
+            .can_claim_draw()
+            .filter(|_| flagged.is_none() && !editing && !reviewing);
+        if draw_claim.is_some()
+            && is_mouse_button_pressed(MouseButton::Left)
+            && layout.claim_draw_button().contains(mouse_vec)
+        {
+            game.claim_draw();
+        }
+        let computers_turn = computer_opponent
+            && game.turn == game::Color::Black
+            && game.result() == game::GameResult::Ongoing
+            && flagged.is_none()
+            && !editing
+            && !reviewing;
+        // an undo or anything else that left the searched position makes the answer useless.
+        // the thread finishes on its own, its send just goes nowhere
+        if thinking
+            .as_ref()
+            .is_some_and(|(_, hash)| !computers_turn || *hash != game.hash())
+        {
+            thinking = None;
+        }
+        if computers_turn {
+            match &thinking {
+                None => thinking = Some(search_in_background(&game, AI_THINK_MILLIS)),
+                Some((receiver, _)) => {
+                    if let Some(reply) = search_reply(receiver) {
+                        thinking = None;
+                        if let Some(mov) = reply {
+                            game.request_move_promoting(&mov.from, &mov.target(), mov.promotion);
+                        }
+                        // premoves promote to a queen, and one the reply made illegal is just
+                        // dropped
+                        if let Some((from, to)) = premove.take() {
+                            if game.result() == game::GameResult::Ongoing {
+                                game.request_move(&from, &to);
+                            }
+                        }
+                        moving_piece = None;
+                        selected_piece = None;
+                    }
+                }
+            }
+        }
+        let humans_turn = !(computer_opponent && game.turn == game::Color::Black);
+        let can_hint = humans_turn
+            && game.result() == game::GameResult::Ongoing
+            && flagged.is_none()
+            && !editing
+            && !reviewing;
+        // like the computer's move, a hint for a position that's gone is no use
+        if hinting
+            .as_ref()
+            .is_some_and(|(_, hash)| !can_hint || *hash != game.hash())
+        {
+            hinting = None;
+        }
+        if can_hint && hinting.is_none() && shortcut(KeyCode::H) {
+            hint = None;
+            hinting = Some(search_in_background(&game, HINT_THINK_MILLIS));
+        }
+        if let Some((receiver, hash)) = &hinting {
+            if let Some(reply) = search_reply(receiver) {
+                hint = reply.map(|m| (m, *hash));
+                hinting = None;
+            }
+        }
+        // a debug view of how the search thinks, its line played out a move at a time on a side
+        // board for as long as P is held
+        let showing_line = !typing && !editing && is_key_down(KeyCode::P);
+        if finding_line
+            .as_ref()
+            .is_some_and(|(_, hash)| *hash != game.hash())
+        {
+            finding_line = None;
+        }
+        if engine_line
+            .as_ref()
+            .is_some_and(|(_, hash)| *hash != game.hash())
+        {
+            engine_line = None;
+        }
+        if showing_line && engine_line.is_none() && finding_line.is_none() {
+            finding_line = Some(best_line_in_background(&game, LINE_THINK_MILLIS));
+        }
+        // pressing P again starts the line over
+        if shortcut(KeyCode::P) {
+            engine_line_start = get_time();
+        }
+        if let Some((receiver, hash)) = &finding_line {
+            match receiver.try_recv() {
+                Ok(line) => {
+                    let mut positions = vec![(String::new(), game.clone())];
+                    for mov in line {
+                        let before = &positions[positions.len() - 1].1;
+                        let san = before.move_to_san(&mov);
+                        let mut after = before.clone();
+                        after.request_move_promoting(&mov.from, &mov.target(), mov.promotion);
+                        positions.push((san, after));
+                    }
+                    engine_line = Some((positions, *hash));
+                    engine_line_start = get_time();
+                    finding_line = None;
+                }
+                Err(TryRecvError::Empty) => {}
+                Err(TryRecvError::Disconnected) => finding_line = None,
+            }
         }
 
-        if let Some(mouse_square) = mouse_square_option {
+        // a game started from the editor, swapped in once the editor lets go of it
+        let mut edited_game = None;
+        if let Some(editor) = &mut editor {
+            if is_key_pressed(KeyCode::Tab) {
+                editor.turn = editor.turn.invert();
+                editor.error = None;
+            }
+            for (i, key) in [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4]
+                .into_iter()
+                .enumerate()
+            {
+                if is_key_pressed(key) {
+                    editor.toggle_castling(i);
+                }
+            }
+            if is_mouse_button_pressed(MouseButton::Left) {
+                if let Some(square) = mouse_square_option {
+                    editor.place(&square);
+                } else if let Some(i) =
+                    (0..palette.len()).find(|i| layout.palette_cell(*i).contains(mouse_vec))
+                {
+                    editor.brush = palette[i];
+                }
+            }
+            if is_mouse_button_pressed(MouseButton::Right) {
+                if let Some(square) = mouse_square_option {
+                    editor.clear(&square);
+                }
+            }
+            if is_key_pressed(KeyCode::Enter) {
+                match editor.start(game.variant) {
+                    Ok(g) => edited_game = Some(g),
+                    Err(e) => editor.error = Some(e),
+                }
+            }
+        // the board is frozen once the game ends, only undo gets out of it. and while reviewing,
+        // until Right gets back to the live game
+        } else if game.result() != game::GameResult::Ongoing || flagged.is_some() || reviewing {
+            moving_piece = None;
+            selected_piece = None;
+            premove = None;
+        } else if !humans_turn {
+            // clicks queue a premove instead, from one of the player's pieces to any other square
+            if is_mouse_button_pressed(MouseButton::Left) {
+                let player = game.turn.invert();
+                match (selected_piece, mouse_square_option) {
+                    (Some(from), Some(to)) if from != to => {
+                        premove = Some((from, to));
+                        selected_piece = None;
+                    }
+                    (_, Some(square))
+                        if game
+                            .piece_at_square(&square)
+                            .is_some_and(|p| p.color == player) =>
+                    {
+                        premove = None;
+                        selected_piece = Some(square);
+                    }
+                    _ => {
+                        premove = None;
+                        selected_piece = None;
+                    }
+                }
+            }
+        } else if let Some((from, to)) = pending_promotion {
+            if is_mouse_button_pressed(MouseButton::Left) {
+                let choice = mouse_square_option.and_then(|mouse_square| {
+                    promotion_choice_squares(&to)
+                        .iter()
+                        .position(|s| *s == mouse_square)
+                });
+                // clicking anywhere else cancels the move
+                if let Some(i) = choice {
+                    game.request_move_promoting(&from, &to, Some(PROMOTION_CHOICES[i]));
+                }
+                pending_promotion = None;
+            }
+        } else if let Some(mouse_square) = mouse_square_option {
             if is_mouse_button_pressed(MouseButton::Left) {
                 let piece_at_square = game.piece_at_square(&mouse_square);
                 if let Some(s) = selected_piece {
                     if s != mouse_square {
                         if let Some(p) = piece_at_square {
-                            if p.color == game.turn {
+                            // clicking one of your own pieces picks it instead, apart from the
+                            // rook of a chess960 castle
+                            if p.color == game.turn && !game.is_legal(&s, &mouse_square) {
                                 moving_piece = Some(mouse_square);
                                 selected_piece = Some(mouse_square);
                             } else {
-                                game.request_move(&s, &mouse_square);
+                                pending_promotion = request_move_or_promotion(
+                                    &mut game,
+                                    &s,
+                                    &mouse_square,
+                                    auto_queen,
+                                );
                                 moving_piece = None;
                                 selected_piece = None;
                             }
                         } else {
-                            game.request_move(&s, &mouse_square);
+                            pending_promotion =
+                                request_move_or_promotion(&mut game, &s, &mouse_square, auto_queen);
                             moving_piece = None;
                             selected_piece = None;
                         }
@@ -170,7 +1033,8 @@ async fn main() {
                         moving_piece = None;
                         // intentionally don't touch selected piece
                     } else if let Some(s) = selected_piece {
-                        game.request_move(&s, &mouse_square);
+                        pending_promotion =
+                            request_move_or_promotion(&mut game, &s, &mouse_square, auto_queen);
                         moving_piece = None;
                         selected_piece = None;
                     }
@@ -181,6 +1045,32 @@ async fn main() {
             selected_piece = None;
         }
 
+        if let Some(g) = edited_game {
+            game = g;
+            editor = None;
+        }
+        let editing = editor.is_some();
+
+        // looked up every frame so it follows undo and clears once the check is answered
+        let checked_king = if !editing && game.is_in_check(game.turn) {
+            game.king_square(game.turn)
+        } else {
+            None
+        };
+        // for the attacked squares overlay, squares with pieces on them count as covered too
+        let attacker = if show_opponent_attacks {
+            game.turn.invert()
+        } else {
+            game.turn
+        };
+        // none at the start of the game or once every move is undone
+        let last_move = game
+            .moves
+            .last()
+            .filter(|_| !editing)
+            .map(|m| (m.from, m.to));
+        // the editor's board stands in for the game's while it's open
+        let board = editor.as_ref().map_or(game.board, |e| e.board);
         for row in 0..8 {
             for col in 0..8 {
                 let mut selected = false;
@@ -189,98 +1079,466 @@ async fn main() {
                         selected = true;
                     }
                 }
+                let (x, y) = layout.square_origin(&(row, col));
                 let (color, inverse_color) = if row % 2 == col % 2 {
-                    (LIGHT_SQUARE, DARK_SQUARE)
+                    (theme.light_square, theme.dark_square)
                 } else {
-                    (DARK_SQUARE, LIGHT_SQUARE)
+                    (theme.dark_square, theme.light_square)
                 };
-                draw_rectangle(
-                    top_left.0 + col as f32 * square_size,
-                    top_left.1 + row as f32 * square_size,
-                    square_size,
-                    square_size,
-                    color,
-                );
-                if col == 0 {
+                draw_rectangle(x, y, square_size, square_size, color);
+                // the ghost goes under everything else on the square, like the square itself
+                if moving_piece == Some((row, col)) {
+                    if let Some(p) = board.get(&(row, col)) {
+                        draw_piece(p, x, y, square_size, WHITE);
+                        let wash = Color {
+                            a: GHOST_WASH,
+                            ..color
+                        };
+                        draw_rectangle(x, y, square_size, square_size, wash);
+                    }
+                }
+                if col == left_col {
                     draw_text_ex(
                         &(8 - row).to_string(),
-                        top_left.0 + col as f32 * square_size + square_size / 50f32,
-                        top_left.1 + row as f32 * square_size + square_size / 5f32,
+                        x + square_size / 50f32,
+                        y + square_size / 5f32,
                         TextParams {
                             font_size: (square_size / 5f32) as u16,
                             color: inverse_color,
-                            font: Some(&font),
+                            font: Some(font),
                             ..Default::default()
                         },
                     );
                 }
-                if row == 7 {
+                if row == bottom_row {
                     draw_text_ex(
                         &"abcdefgh".chars().nth((col) as usize).unwrap().to_string(),
-                        top_left.0 + col as f32 * square_size + square_size * (6f32 / 7f32),
-                        top_left.1 + row as f32 * square_size + square_size * (49f32 / 50f32),
+                        x + square_size * (6f32 / 7f32),
+                        y + square_size * (49f32 / 50f32),
                         TextParams {
                             font_size: (square_size / 5f32) as u16,
                             color: inverse_color,
-                            font: Some(&font),
+                            font: Some(font),
                             ..Default::default()
                         },
                     );
                 }
+                if let Some((from, to)) = last_move {
+                    if from == (row, col) || to == (row, col) {
+                        draw_rectangle(x, y, square_size, square_size, LAST_MOVE);
+                    }
+                }
+                if let Some((from, to)) = premove {
+                    if from == (row, col) || to == (row, col) {
+                        draw_rectangle(x, y, square_size, square_size, PREMOVE);
+                    }
+                }
+                if checked_king == Some((row, col)) {
+                    draw_rectangle(x, y, square_size, square_size, CHECK);
+                }
                 if selected {
-                    draw_rectangle(
-                        top_left.0 + col as f32 * square_size,
-                        top_left.1 + row as f32 * square_size,
-                        square_size,
-                        square_size,
-                        SELECTED,
-                    );
+                    draw_rectangle(x, y, square_size, square_size, theme.selected);
                 }
-                if let Some(p) = game.piece_at_square(&(row, col)) {
-                    // draw moving piece at half opacity
-                    let mut color = WHITE;
-                    if let Some(m) = moving_piece {
-                        if m == (row, col) {
-                            color = color_u8!(0xff, 0xff, 0xff, 0x7f);
-                        }
+                if show_attacked && !editing && game.is_square_attacked(&(row, col), attacker) {
+                    draw_rectangle(x, y, square_size, square_size, ATTACKED);
+                }
+                if let Some(p) = board.get(&(row, col)) {
+                    if moving_piece != Some((row, col)) {
+                        draw_piece(p, x, y, square_size, WHITE);
                     }
-                    draw_piece(
-                        p,
-                        top_left.0 + col as f32 * square_size,
-                        top_left.1 + row as f32 * square_size,
-                        square_size,
-                        color,
-                    );
                 };
             }
         }
+        // side panel
+        draw_rectangle(
+            layout.right_panel.x,
+            layout.right_panel.y,
+            layout.right_panel.w,
+            layout.right_panel.h,
+            theme.panel,
+        );
+        // move list, the played moves then the ones redo would bring back
+        let line: Vec<game::Move> = game
+            .moves
+            .iter()
+            .chain(game.redo.iter().rev())
+            .cloned()
+            .collect();
+        if line != move_list_line {
+            move_list_san = game.san_line();
+            move_list_line = line;
+        }
+        let visible_rows = layout.move_list_rows();
+        let total_rows = (move_list_san.len() + black_first as usize).div_ceil(2);
+        if game.moves.len() != move_list_ply {
+            move_list_ply = game.moves.len();
+            // keep the current move on screen
+            let current_row = (move_list_ply + black_first as usize).saturating_sub(1) / 2;
+            if current_row < move_list_scroll {
+                move_list_scroll = current_row;
+            } else if current_row >= move_list_scroll + visible_rows {
+                move_list_scroll = current_row + 1 - visible_rows;
+            }
+        }
+        move_list_scroll = move_list_scroll.min(total_rows.saturating_sub(visible_rows));
+        let move_list_text = |text: &str, cell: Rect, color: Color| {
+            draw_text_ex(
+                text,
+                cell.x + cell.h / 4f32,
+                cell.y + cell.h * (3f32 / 4f32),
+                TextParams {
+                    font_size: (cell.h / 2f32) as u16,
+                    color,
+                    font: Some(font),
+                    ..Default::default()
+                },
+            );
+        };
+        for (ply, san) in move_list_san.iter().enumerate() {
+            let slot = ply + black_first as usize;
+            let (row, column) = (slot / 2, 1 + slot % 2);
+            if row < move_list_scroll || row >= move_list_scroll + visible_rows {
+                continue;
+            }
+            if column == 1 || ply == 0 {
+                let number = first_number + row as u16;
+                let number = if column == 1 {
+                    format!("{}.", number)
+                } else {
+                    format!("{}...", number)
+                };
+                let cell = layout.move_list_cell(row - move_list_scroll, 0);
+                move_list_text(&number, cell, theme.dark_square);
+            }
+            let cell = layout.move_list_cell(row - move_list_scroll, column);
+            if ply + 1 == game.moves.len() {
+                draw_rectangle(cell.x, cell.y, cell.w, cell.h, LAST_MOVE);
+            }
+            // undone moves are dimmed
+            let color = if ply < game.moves.len() {
+                theme.light_square
+            } else {
+                theme.dark_square
+            };
+            move_list_text(san, cell, color);
+        }
+        // the editor's palette covers the move list
+        if let Some(editor) = &editor {
+            draw_rectangle(
+                layout.move_list.x,
+                layout.move_list.y,
+                layout.move_list.w,
+                layout.move_list.h,
+                theme.panel,
+            );
+            for (i, piece) in palette.iter().enumerate() {
+                let cell = layout.palette_cell(i);
+                if *piece == editor.brush {
+                    draw_rectangle(cell.x, cell.y, cell.w, cell.h, theme.selected);
+                }
+                match piece {
+                    Some(p) => draw_piece(p, cell.x, cell.y, cell.w, WHITE),
+                    // the eraser is a cross
+                    None => {
+                        let inset = cell.w / 4f32;
+                        let (left, right) = (cell.x + inset, cell.x + cell.w - inset);
+                        let (top, bottom) = (cell.y + inset, cell.y + cell.h - inset);
+                        draw_line(left, top, right, bottom, inset / 3f32, theme.light_square);
+                        draw_line(left, bottom, right, top, inset / 3f32, theme.light_square);
+                    }
+                }
+            }
+        }
+        // the engine's line covers the move list too, looping back to the start once it's played
+        if let Some((positions, _)) = engine_line.as_ref().filter(|_| showing_line) {
+            draw_rectangle(
+                layout.move_list.x,
+                layout.move_list.y,
+                layout.move_list.w,
+                layout.move_list.h,
+                theme.panel,
+            );
+            let step =
+                ((get_time() - engine_line_start) / LINE_STEP_SECONDS) as usize % positions.len();
+            let (san, position) = &positions[step];
+            let moved = position
+                .moves
+                .last()
+                .filter(|_| step > 0)
+                .map(|m| (m.from, m.to));
+            for row in 0..8 {
+                for col in 0..8 {
+                    let cell = layout.side_board_square(&(row, col));
+                    let color = if row % 2 == col % 2 {
+                        theme.light_square
+                    } else {
+                        theme.dark_square
+                    };
+                    draw_rectangle(cell.x, cell.y, cell.w, cell.h, color);
+                    if moved.is_some_and(|(from, to)| from == (row, col) || to == (row, col)) {
+                        draw_rectangle(cell.x, cell.y, cell.w, cell.h, LAST_MOVE);
+                    }
+                    if let Some(p) = position.board.get(&(row, col)) {
+                        draw_piece(p, cell.x, cell.y, cell.w, WHITE);
+                    }
+                }
+            }
+            let text = match step {
+                0 => format!("Engine line, {} moves", positions.len() - 1),
+                _ => format!("{} of {}: {}", step, positions.len() - 1, san),
+            };
+            let size = layout.side_board_square(&(0, 0)).w;
+            let cell = Rect::new(
+                layout.move_list.x,
+                layout.move_list.y + size * 8f32,
+                layout.move_list.w,
+                layout.move_list_row_height(),
+            );
+            move_list_text(&text, cell, theme.light_square);
+        }
+        // captured pieces, each side's strip is at its edge of the board
+        let bottom_color = if layout.flipped {
+            game::Color::Black
+        } else {
+            game::Color::White
+        };
+        let advantage =
+            game.material_count(bottom_color) - game.material_count(bottom_color.invert());
+        for (strip, color, ahead) in [
+            (layout.captured_bottom, bottom_color, advantage),
+            (layout.captured_top, bottom_color.invert(), -advantage),
+        ] {
+            let size = strip.h;
+            let mut x = strip.x + size / 4f32;
+            let mut previous = None;
+            for piece_type in captured_by(&game, color) {
+                // pieces of the same type overlap, with a gap before the next type
+                if previous.is_some_and(|p| p != piece_type) {
+                    x += size / 2f32;
+                }
+                let piece = game::Piece {
+                    piece_type,
+                    color: color.invert(),
+                };
+                draw_piece(&piece, x, strip.y, size, WHITE);
+                x += size / 2f32;
+                previous = Some(piece_type);
+            }
+            if let Some(clocks) = &clocks {
+                // right aligned, brighter for the side whose clock is running
+                let text = clock::format_time(clocks.remaining(color));
+                let font_size = (size * 0.8f32) as u16;
+                let width = measure_text(&text, Some(font), font_size, 1f32).width;
+                draw_text_ex(
+                    &text,
+                    strip.x + strip.w - width - size / 4f32,
+                    strip.y + size * (3f32 / 4f32),
+                    TextParams {
+                        font_size,
+                        color: if color == game.turn {
+                            theme.light_square
+                        } else {
+                            theme.dark_square
+                        },
+                        font: Some(font),
+                        ..Default::default()
+                    },
+                );
+            }
+            if ahead > 0 {
+                draw_text_ex(
+                    &format!("+{}", ahead / 100),
+                    x + size,
+                    strip.y + size * (3f32 / 4f32),
+                    TextParams {
+                        font_size: (size * 0.6f32) as u16,
+                        color: theme.light_square,
+                        font: Some(font),
+                        ..Default::default()
+                    },
+                );
+            }
+        }
+        // clicks, the computer and redo all end up here, undo stays quiet
+        if game.moves.len() > played_moves {
+            audio.play(sounds::effect_for(&game));
+            if let Some(clocks) = clocks.as_mut().filter(|_| !replayed) {
+                // the newest move was by the side not to move, and they alternate back from there
+                for ply in played_moves..game.moves.len() {
+                    let mover = if (game.moves.len() - ply) % 2 == 1 {
+                        game.turn.invert()
+                    } else {
+                        game.turn
+                    };
+                    clocks.add_increment(mover);
+                }
+            }
+        }
+        played_moves = game.moves.len();
+
+        let result = game.result();
+        // running out of time ends the game too, on top of what the board says
+        let game_over = match flagged {
+            Some(color) => Some(format!("{} wins on time", color.invert())),
+            None if result != game::GameResult::Ongoing => Some(result.to_string()),
+            None => None,
+        };
+        // the mistake being stepped through, while the board is on the position it was made in
+        let shown_mistake = mistakes
+            .as_ref()
+            .and_then(|(found, index, _)| found.get(*index))
+            .filter(|m| review.is_some() && game.moves.len() == m.ply);
+        // status bar
+        let mut status = match &editor {
+            Some(editor) => {
+                let mut status = format!(
+                    "Editing, {} to move, castling {} (Tab side, 1-4 castling, Enter to start)",
+                    editor.turn,
+                    editor.castling_string()
+                );
+                if let Some(e) = &editor.error {
+                    status = format!("Can't start: {}", e);
+                }
+                status
+            }
+            None => game_over
+                .clone()
+                .unwrap_or_else(|| format!("{} to move", game.turn)),
+        };
+        if game.variant == game::Variant::ThreeCheck {
+            status.push_str(&format!(
+                " (checks: White {}, Black {})",
+                game.checks(game::Color::White),
+                game.checks(game::Color::Black)
+            ));
+        }
+        if let Some(color) = game.draw_offer {
+            status.push_str(&format!(" ({} offers a draw, D to accept)", color));
+        }
+        if let Some(claim) = draw_claim {
+            status.push_str(&format!(" (draw by {} can be claimed)", claim));
+        }
+        if thinking.is_some() {
+            status.push_str(" (computer thinking...)");
+        } else if computer_opponent {
+            status.push_str(" (computer plays Black)");
+        }
+        if hinting.is_some() {
+            status.push_str(" (finding a hint...)");
+        }
+        if auto_queen {
+            status.push_str(" (auto queen)");
+        }
+        if let Some(live) = &review {
+            status = format!(
+                "Reviewing move {} of {} (Left and Right to step)",
+                game.moves.len(),
+                live.moves.len()
+            );
+        }
+        if let Some((found, index, _)) = &mistakes {
+            status = match shown_mistake {
+                _ if found.is_empty() => "No big mistakes found (Esc to close)".to_owned(),
+                Some(m) => {
+                    let swing = if m.swing >= ai::MATE_SWING {
+                        "a mate".to_owned()
+                    } else {
+                        format!("{:.1}", m.swing as f32 / 100f32)
+                    };
+                    let dots = if game.turn == game::Color::White {
+                        "."
+                    } else {
+                        "..."
+                    };
+                    format!(
+                        "{}{} {} swings the eval by {}, {} was better (mistake {} of {}, Left and Right to step, Esc to stop)",
+                        game.fullmove_number,
+                        dots,
+                        game.move_to_san(&m.played),
+                        swing,
+                        game.move_to_san(&m.better),
+                        index + 1,
+                        found.len()
+                    )
+                }
+                None => format!(
+                    "Mistake {} of {} (Left and Right to step, Esc to stop)",
+                    index + 1,
+                    found.len()
+                ),
+            };
+        } else if finding_mistakes.is_some() {
+            status.push_str(" (looking for mistakes...)");
+        }
+        if finding_line.is_some() && showing_line {
+            status.push_str(" (working out the engine's line...)");
+        }
+        if let Some((TextInput::Move, text)) = &text_input {
+            status = format!("Move: {}_ (Enter to play, Esc to cancel)", text);
+        } else if let Some((TextInput::Fen, text)) = &text_input {
+            status = match &fen_input_error {
+                Some(e) => format!("FEN: {}_ ({})", text, e),
+                None => format!("FEN: {}_ (Enter to load, Esc to cancel)", text),
+            };
+        } else if let Some((text, at)) = &move_input_error {
+            if get_time() - at < MOVE_INPUT_ERROR_SECONDS {
+                status = format!("Can't play \"{}\"", text);
+            }
+        }
+        draw_text_ex(
+            &status,
+            layout.top_bar.x + layout.top_bar.h / 4f32,
+            layout.top_bar.y + layout.top_bar.h * (3f32 / 4f32),
+            TextParams {
+                font_size: (layout.top_bar.h / 2f32) as u16,
+                color: theme.light_square,
+                font: Some(font),
+                ..Default::default()
+            },
+        );
+        if draw_claim.is_some() {
+            let button = layout.claim_draw_button();
+            draw_rectangle(button.x, button.y, button.w, button.h, theme.selected);
+            draw_text_ex(
+                "Claim draw",
+                button.x + button.h / 4f32,
+                button.y + button.h * (3f32 / 4f32),
+                TextParams {
+                    font_size: (button.h / 2f32) as u16,
+                    color: theme.light_square,
+                    font: Some(font),
+                    ..Default::default()
+                },
+            );
+        }
         // draw selected squares
         if let Some(s) = selected_piece {
+            // holding shift only shows captures, for hunting tactics
+            let captures_only = shift;
             for mov in game.legal_moves_on_square(s) {
-                let (row, col) = mov.to;
-                let offset = (
-                    top_left.0 + col as f32 * square_size,
-                    top_left.1 + row as f32 * square_size,
-                );
+                if captures_only && mov.capture.is_none() {
+                    continue;
+                }
+                let offset = layout.square_origin(&mov.target());
                 if mov.capture.is_some() {
                     // a capturing move
                     draw_triangle(
                         vec2(offset.0, offset.1),
                         vec2(offset.0 + square_size / 4f32, offset.1),
                         vec2(offset.0, offset.1 + square_size / 4f32),
-                        SELECTED,
+                        theme.selected,
                     );
                     draw_triangle(
                         vec2(offset.0 + square_size, offset.1),
                         vec2(offset.0 + square_size - square_size / 4f32, offset.1),
                         vec2(offset.0 + square_size, offset.1 + square_size / 4f32),
-                        SELECTED,
+                        theme.selected,
                     );
                     draw_triangle(
                         vec2(offset.0, offset.1 + square_size),
                         vec2(offset.0 + square_size / 4f32, offset.1 + square_size),
                         vec2(offset.0, offset.1 + square_size - square_size / 4f32),
-                        SELECTED,
+                        theme.selected,
                     );
                     draw_triangle(
                         vec2(offset.0 + square_size, offset.1 + square_size),
@@ -292,7 +1550,7 @@ async fn main() {
                             offset.0 + square_size,
                             offset.1 + square_size - square_size / 4f32,
                         ),
-                        SELECTED,
+                        theme.selected,
                     );
                 } else {
                     // empty square
@@ -300,12 +1558,96 @@ async fn main() {
                         offset.0 + square_size / 2f32,
                         offset.1 + square_size / 2f32,
                         square_size / 10f32,
-                        SELECTED,
+                        theme.selected,
                     )
                 }
             }
         }
 
+        let center = |square: &game::Square| {
+            let (x, y) = layout.square_origin(square);
+            vec2(x + square_size / 2f32, y + square_size / 2f32)
+        };
+        if let Some((mov, _)) = hint
+            .as_ref()
+            .filter(|(_, hash)| !editing && *hash == game.hash())
+        {
+            draw_arrow(center(&mov.from), center(&mov.to), square_size / 8f32, HINT);
+        }
+        // the move that should have been played instead, drawn like a hint
+        if let Some(m) = shown_mistake {
+            draw_arrow(
+                center(&m.better.from),
+                center(&m.better.to),
+                square_size / 8f32,
+                HINT,
+            );
+        }
+
+        // promotion choices over the board, the player has to pick one or click away
+        if let Some((_, to)) = pending_promotion {
+            draw_rectangle(
+                layout.board.x,
+                layout.board.y,
+                layout.board.w,
+                layout.board.h,
+                OVERLAY_SHADE,
+            );
+            for (square, piece_type) in promotion_choice_squares(&to).iter().zip(PROMOTION_CHOICES)
+            {
+                let (x, y) = layout.square_origin(square);
+                draw_rectangle(x, y, square_size, square_size, theme.light_square);
+                let piece = game::Piece {
+                    piece_type,
+                    color: game.turn,
+                };
+                draw_piece(&piece, x, y, square_size, WHITE);
+            }
+        }
+
+        if let Some(game_over) = game_over.filter(|_| !editing && review.is_none()) {
+            draw_rectangle(
+                layout.board.x,
+                layout.board.y,
+                layout.board.w,
+                layout.board.h,
+                OVERLAY_SHADE,
+            );
+            // a strip across the middle of the board with the result and a hint under it
+            let banner_height = layout.board.h / 4f32;
+            let banner_y = layout.board.y + (layout.board.h - banner_height) / 2f32;
+            draw_rectangle(
+                layout.board.x,
+                banner_y,
+                layout.board.w,
+                banner_height,
+                theme.panel,
+            );
+            // undo can't bring back time, so a loss on time is final
+            let hint = if flagged.is_some() {
+                "Left to review, B for mistakes"
+            } else {
+                "Z to undo, Left to review, B for mistakes"
+            };
+            for (text, font_size, baseline) in [
+                (game_over, layout.board.w / 14f32, 0.5f32),
+                (hint.to_owned(), layout.board.w / 28f32, 0.8f32),
+            ] {
+                let size = measure_text(&text, Some(font), font_size as u16, 1f32);
+                draw_text_ex(
+                    &text,
+                    layout.board.x + (layout.board.w - size.width) / 2f32,
+                    banner_y + banner_height * baseline,
+                    TextParams {
+                        font_size: font_size as u16,
+                        color: theme.light_square,
+                        font: Some(font),
+                        ..Default::default()
+                    },
+                );
+            }
+        }
+
         // draw held piece
         if let Some(p) = moving_piece {
             if let Some(m) = game.piece_at_square(&p) {
@@ -0,0 +1,421 @@
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::game::{Castling, Color, Game, Move, PieceType, Square};
+
+mod random64;
+
+use random64::RANDOM64;
+
+// the first few moves of the main openings, see the tests for the lines it was built from
+const EMBEDDED_BOOK: &[u8] = include_bytes!("../assets/book.bin");
+
+// where the castling, en passant and turn keys start in RANDOM64, after the 12 * 64 piece keys
+const CASTLING_KEYS: usize = 768;
+const EN_PASSANT_KEYS: usize = 772;
+const WHITE_TO_MOVE_KEY: usize = 780;
+
+// polyglot squares count ranks up from white's side and ours count rows down from black's
+fn polyglot_square((row, col): &Square) -> usize {
+    (7 - *row as usize) * 8 + *col as usize
+}
+
+// the position's key in polyglot books, not the same keys as Game::zobrist
+pub fn polyglot_key(game: &Game) -> u64 {
+    let mut key = 0;
+    for (square, piece) in game.board.pieces() {
+        // black pawn, white pawn, black knight... white king, same order as PieceType
+        let kind = piece.piece_type as usize * 2 + piece.color as usize;
+        key ^= RANDOM64[kind * 64 + polyglot_square(&square)];
+    }
+    let rights = &game.castling_rights;
+    for (i, right) in [
+        rights.white_kingside,
+        rights.white_queenside,
+        rights.black_kingside,
+        rights.black_queenside,
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        if right {
+            key ^= RANDOM64[CASTLING_KEYS + i];
+        }
+    }
+    // the en passant file only counts when a pawn of the side to move stands next to the pawn
+    // that double moved, whether or not taking it would be legal
+    if let Some((row, col)) = game.en_passant_target_square {
+        let pawn_row = match game.turn {
+            Color::White => row + 1,
+            Color::Black => row - 1,
+        };
+        let can_take = [col - 1, col + 1].iter().any(|c| {
+            game.piece_at_square(&(pawn_row, *c))
+                .is_some_and(|p| p.piece_type == PieceType::Pawn && p.color == game.turn)
+        });
+        if can_take {
+            key ^= RANDOM64[EN_PASSANT_KEYS + col as usize];
+        }
+    }
+    if game.turn == Color::White {
+        key ^= RANDOM64[WHITE_TO_MOVE_KEY];
+    }
+    key
+}
+
+// one 16 byte record of a polyglot .bin book, stored big endian
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BookEntry {
+    pub key: u64,
+    // to file, to rank, from file, from rank in 3 bits each from the bottom, then the promotion
+    pub mov: u16,
+    pub weight: u16,
+    // unused by most books, kept so entries round trip
+    pub learn: u32,
+}
+
+const ENTRY_SIZE: usize = 16;
+
+#[derive(Debug)]
+pub enum BookError {
+    Io(std::io::Error),
+    // a book is whole entries, anything left over means a truncated or wrong file
+    Length(usize),
+}
+
+impl Display for BookError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BookError::Io(e) => write!(f, "couldn't read the book: {}", e),
+            BookError::Length(n) => write!(
+                f,
+                "book is {} bytes, not a multiple of the {} byte entry size",
+                n, ENTRY_SIZE
+            ),
+        }
+    }
+}
+
+impl From<std::io::Error> for BookError {
+    fn from(e: std::io::Error) -> Self {
+        BookError::Io(e)
+    }
+}
+
+pub struct OpeningBook {
+    // sorted by key like the file, so a position's moves sit next to each other
+    entries: Vec<BookEntry>,
+}
+
+impl OpeningBook {
+    pub fn from_bytes(bytes: &[u8]) -> Result<OpeningBook, BookError> {
+        if !bytes.len().is_multiple_of(ENTRY_SIZE) {
+            return Err(BookError::Length(bytes.len()));
+        }
+        let mut entries: Vec<BookEntry> = bytes
+            .chunks_exact(ENTRY_SIZE)
+            .map(|chunk| BookEntry {
+                key: u64::from_be_bytes(chunk[0..8].try_into().unwrap()),
+                mov: u16::from_be_bytes(chunk[8..10].try_into().unwrap()),
+                weight: u16::from_be_bytes(chunk[10..12].try_into().unwrap()),
+                learn: u32::from_be_bytes(chunk[12..16].try_into().unwrap()),
+            })
+            .collect();
+        // books are written sorted, but sorting is cheap insurance for hand made ones
+        entries.sort_by_key(|e| e.key);
+        Ok(OpeningBook { entries })
+    }
+    pub fn load(path: impl AsRef<Path>) -> Result<OpeningBook, BookError> {
+        OpeningBook::from_bytes(&std::fs::read(path)?)
+    }
+    // the book built into the engine
+    pub fn embedded() -> &'static OpeningBook {
+        static BOOK: OnceLock<OpeningBook> = OnceLock::new();
+        BOOK.get_or_init(|| OpeningBook::from_bytes(EMBEDDED_BOOK).expect("embedded book is valid"))
+    }
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    // every entry for the position with this polyglot key
+    pub fn entries(&self, key: u64) -> &[BookEntry] {
+        let start = self.entries.partition_point(|e| e.key < key);
+        let end = self.entries.partition_point(|e| e.key <= key);
+        &self.entries[start..end]
+    }
+    // the book's moves for the game's position with their weights. entries that aren't legal
+    // here, from a key collision or a broken book, are skipped
+    pub fn moves(&self, game: &Game) -> Vec<(Move, u16)> {
+        self.entries(polyglot_key(game))
+            .iter()
+            .filter_map(|e| decode_move(game, e.mov).map(|m| (m, e.weight)))
+            .collect()
+    }
+    // a book move picked in proportion to its weight. roll is any random number, the library
+    // has no randomness of its own. None when the position isn't in the book
+    pub fn pick(&self, game: &Game, roll: u64) -> Option<Move> {
+        let moves = self.moves(game);
+        let total: u64 = moves.iter().map(|(_, w)| *w as u64).sum();
+        if total == 0 {
+            // all zero weights still mean the moves are in the book, just take the first
+            return moves.into_iter().next().map(|(m, _)| m);
+        }
+        let mut roll = roll % total;
+        moves.into_iter().find_map(|(m, w)| {
+            if roll < w as u64 {
+                Some(m)
+            } else {
+                roll -= w as u64;
+                None
+            }
+        })
+    }
+}
+
+fn decode_square(bits: u16) -> Square {
+    let file = (bits & 7) as i8;
+    let rank = ((bits >> 3) & 7) as i8;
+    (7 - rank, file)
+}
+
+// the legal move a book entry stands for. castling is stored as the king taking its own rook
+fn decode_move(game: &Game, mov: u16) -> Option<Move> {
+    let to = decode_square(mov & 0x3f);
+    let from = decode_square((mov >> 6) & 0x3f);
+    let promotion = match (mov >> 12) & 7 {
+        0 => None,
+        1 => Some(PieceType::Knight),
+        2 => Some(PieceType::Bishop),
+        3 => Some(PieceType::Rook),
+        4 => Some(PieceType::Queen),
+        _ => return None,
+    };
+    let moving = (*game.piece_at_square(&from))?;
+    let onto_own_rook = game
+        .piece_at_square(&to)
+        .is_some_and(|p| p.color == moving.color && p.piece_type == PieceType::Rook);
+    if moving.piece_type == PieceType::King && onto_own_rook {
+        let kingside = to.1 > from.1;
+        return game
+            .legal_moves_on_square(from)
+            .iter()
+            .find(|m| match m.castle {
+                Some(Castling::WhiteKingside | Castling::BlackKingside) => kingside,
+                Some(Castling::WhiteQueenside | Castling::BlackQueenside) => !kingside,
+                None => false,
+            })
+            .cloned();
+    }
+    game.legal_moves_on_square(from)
+        .iter()
+        .find(|m| m.to == to && m.promotion == promotion)
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the lines the embedded book was built from. each move's weight is how many lines play it
+    // from that position
+    const LINES: [&str; 16] = [
+        "e2e4 e7e5 g1f3 b8c6 f1b5 a7a6 b5a4 g8f6 e1g1",
+        "e2e4 e7e5 g1f3 b8c6 f1c4 f8c5 c2c3 g8f6",
+        "e2e4 e7e5 g1f3 b8c6 d2d4 e5d4 f3d4",
+        "e2e4 c7c5 g1f3 d7d6 d2d4 c5d4 f3d4 g8f6 b1c3",
+        "e2e4 c7c5 g1f3 b8c6 d2d4 c5d4 f3d4",
+        "e2e4 c7c5 g1f3 e7e6 d2d4 c5d4 f3d4",
+        "e2e4 e7e6 d2d4 d7d5 b1c3 g8f6",
+        "e2e4 c7c6 d2d4 d7d5 b1c3 d5e4 c3e4",
+        "d2d4 d7d5 c2c4 e7e6 b1c3 g8f6",
+        "d2d4 d7d5 c2c4 c7c6 g1f3 g8f6",
+        "d2d4 g8f6 c2c4 e7e6 g1f3 d7d5",
+        "d2d4 g8f6 c2c4 g7g6 b1c3 f8g7 e2e4 d7d6",
+        "d2d4 g8f6 c2c4 e7e6 b1c3 f8b4",
+        "c2c4 e7e5 b1c3 g8f6 g1f3 b8c6",
+        "c2c4 g8f6 b1c3 e7e5",
+        "g1f3 d7d5 d2d4 g8f6 c2c4",
+    ];
+
+    fn encode_square(square: &Square) -> u16 {
+        polyglot_square(square) as u16
+    }
+
+    // castles are written as the king taking its own rook, like decode_move reads them
+    fn encode_move(mov: &Move) -> u16 {
+        let to = match mov.castle {
+            Some(Castling::WhiteKingside | Castling::BlackKingside) => (mov.from.0, 7),
+            Some(Castling::WhiteQueenside | Castling::BlackQueenside) => (mov.from.0, 0),
+            None => mov.to,
+        };
+        let promotion = match mov.promotion {
+            None => 0,
+            Some(PieceType::Knight) => 1,
+            Some(PieceType::Bishop) => 2,
+            Some(PieceType::Rook) => 3,
+            _ => 4,
+        };
+        encode_square(&to) | encode_square(&mov.from) << 6 | promotion << 12
+    }
+
+    fn build_book() -> Vec<u8> {
+        let mut entries: Vec<BookEntry> = vec![];
+        for line in LINES {
+            let mut game = Game::default();
+            for uci in line.split(' ') {
+                let mov = game.parse_uci(uci).unwrap();
+                let key = polyglot_key(&game);
+                let encoded = encode_move(&mov);
+                match entries
+                    .iter_mut()
+                    .find(|e| e.key == key && e.mov == encoded)
+                {
+                    Some(entry) => entry.weight += 1,
+                    None => entries.push(BookEntry {
+                        key,
+                        mov: encoded,
+                        weight: 1,
+                        learn: 0,
+                    }),
+                }
+                assert!(game.request_move_promoting(&mov.from, &mov.target(), mov.promotion));
+            }
+        }
+        // by key, then the most played first like polyglot writes them
+        entries.sort_by_key(|e| (e.key, u16::MAX - e.weight, e.mov));
+        entries
+            .iter()
+            .flat_map(|e| {
+                [
+                    &e.key.to_be_bytes()[..],
+                    &e.mov.to_be_bytes(),
+                    &e.weight.to_be_bytes(),
+                    &e.learn.to_be_bytes(),
+                ]
+                .concat()
+            })
+            .collect()
+    }
+
+    // the test positions published with the polyglot format
+    #[test]
+    fn published_keys() {
+        for (fen, key) in [
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                0x463b96181691fc9c,
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+                0x823c9b50fd114196,
+            ),
+            (
+                "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2",
+                0x0756b94461c50fb0,
+            ),
+            (
+                "rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 2",
+                0x662fafb965db29d4,
+            ),
+            (
+                "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+                0x22a48b5a8e47ff78,
+            ),
+            (
+                "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPPKPPP/RNBQ1BNR b kq - 0 3",
+                0x652a607ca3f242c1,
+            ),
+            (
+                "rnbq1bnr/ppp1pkpp/8/3pPp2/8/8/PPPPKPPP/RNBQ1BNR w - - 0 4",
+                0x00fdd303c946bdd9,
+            ),
+            (
+                "rnbqkbnr/p1pppppp/8/8/PpP4P/8/1P1PPPP1/RNBQKBNR b KQkq c3 0 3",
+                0x3c8123ea7b067637,
+            ),
+            (
+                "rnbqkbnr/p1pppppp/8/8/P6P/R1p5/1P1PPPP1/1NBQKBNR b Kkq - 0 4",
+                0x5c3f9b829b279560,
+            ),
+        ] {
+            assert_eq!(polyglot_key(&Game::from_fen(fen).unwrap()), key, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn embedded_book_is_built_from_its_lines() {
+        assert!(build_book() == EMBEDDED_BOOK);
+        assert_eq!(
+            OpeningBook::embedded().len(),
+            EMBEDDED_BOOK.len() / ENTRY_SIZE
+        );
+    }
+
+    #[test]
+    fn start_position() {
+        let book = OpeningBook::embedded();
+        let game = Game::default();
+        let moves: Vec<(String, u16)> = book
+            .moves(&game)
+            .iter()
+            .map(|(m, w)| (m.to_uci(), *w))
+            .collect();
+        assert_eq!(
+            moves,
+            [
+                ("e2e4".to_string(), 8),
+                ("d2d4".to_string(), 5),
+                ("c2c4".to_string(), 2),
+                ("g1f3".to_string(), 1)
+            ]
+        );
+        // the roll picks through the weights in order
+        let pick = |roll| book.pick(&game, roll).unwrap().to_uci();
+        assert_eq!(pick(0), "e2e4");
+        assert_eq!(pick(7), "e2e4");
+        assert_eq!(pick(8), "d2d4");
+        assert_eq!(pick(15), "g1f3");
+        assert_eq!(pick(16), "e2e4");
+    }
+
+    #[test]
+    fn castling_from_the_book() {
+        let book = OpeningBook::embedded();
+        let mut game = Game::default();
+        for uci in LINES[0].split(' ').take(8) {
+            let mov = game.parse_uci(uci).unwrap();
+            assert!(game.request_move(&mov.from, &mov.to));
+        }
+        let mov = book.pick(&game, 0).unwrap();
+        assert_eq!(mov.castle, Some(Castling::WhiteKingside));
+        // and off the end of the lines there's nothing
+        assert!(game.request_move(&mov.from, &mov.to));
+        assert_eq!(book.pick(&game, 0), None);
+    }
+
+    #[test]
+    fn bad_books() {
+        assert!(matches!(
+            OpeningBook::from_bytes(&[0; 17]),
+            Err(BookError::Length(17))
+        ));
+        assert!(OpeningBook::from_bytes(&[]).unwrap().is_empty());
+        // an entry that doesn't fit the position is skipped rather than played
+        let game = Game::default();
+        let mut bytes = polyglot_key(&game).to_be_bytes().to_vec();
+        // e1e5 isn't a move
+        bytes.extend(
+            [
+                &(encode_square(&(3, 4)) | encode_square(&(7, 4)) << 6).to_be_bytes()[..],
+                &[0, 1, 0, 0, 0, 0],
+            ]
+            .concat(),
+        );
+        let book = OpeningBook::from_bytes(&bytes).unwrap();
+        assert_eq!(book.len(), 1);
+        assert_eq!(book.pick(&game, 0), None);
+    }
+}
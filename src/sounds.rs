@@ -0,0 +1,97 @@
+#[cfg(feature = "sound")]
+use macroquad::audio::{load_sound_from_bytes, play_sound_once, Sound};
+
+#[cfg(feature = "sound")]
+use crate::assets::AssetError;
+use crate::game::{Game, GameResult};
+
+#[cfg(feature = "sound")]
+const MOVE_FILE: &[u8] = include_bytes!("../assets/move.wav");
+#[cfg(feature = "sound")]
+const CAPTURE_FILE: &[u8] = include_bytes!("../assets/capture.wav");
+#[cfg(feature = "sound")]
+const CHECK_FILE: &[u8] = include_bytes!("../assets/check.wav");
+#[cfg(feature = "sound")]
+const GAME_END_FILE: &[u8] = include_bytes!("../assets/game_end.wav");
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Effect {
+    Move,
+    Capture,
+    Check,
+    GameEnd,
+}
+
+// what the move that was just played sounds like, the most important thing about it wins
+pub fn effect_for(game: &Game) -> Effect {
+    if game.result() != GameResult::Ongoing {
+        Effect::GameEnd
+    } else if game.is_in_check(game.turn) {
+        Effect::Check
+    } else if game.moves.last().is_some_and(|m| m.capture.is_some()) {
+        Effect::Capture
+    } else {
+        Effect::Move
+    }
+}
+
+#[cfg(feature = "sound")]
+struct Loaded {
+    move_sound: Sound,
+    capture: Sound,
+    check: Sound,
+    game_end: Sound,
+}
+
+pub struct Sounds {
+    // None if audio failed to load, the game just plays silently then
+    #[cfg(feature = "sound")]
+    loaded: Option<Loaded>,
+}
+
+#[cfg(feature = "sound")]
+async fn load_sound(asset: &'static str, bytes: &[u8]) -> Result<Sound, AssetError> {
+    load_sound_from_bytes(bytes)
+        .await
+        .map_err(|error| AssetError { asset, error })
+}
+
+impl Sounds {
+    // always silent without the sound feature
+    #[cfg(not(feature = "sound"))]
+    pub async fn load() -> Self {
+        Sounds {}
+    }
+    #[cfg(feature = "sound")]
+    pub async fn load() -> Self {
+        let loaded = async {
+            Ok::<_, AssetError>(Loaded {
+                move_sound: load_sound("move.wav", MOVE_FILE).await?,
+                capture: load_sound("capture.wav", CAPTURE_FILE).await?,
+                check: load_sound("check.wav", CHECK_FILE).await?,
+                game_end: load_sound("game_end.wav", GAME_END_FILE).await?,
+            })
+        }
+        .await;
+        match loaded {
+            Ok(l) => Sounds { loaded: Some(l) },
+            Err(e) => {
+                eprintln!("{}, continuing without sound", e);
+                Sounds { loaded: None }
+            }
+        }
+    }
+    #[cfg(not(feature = "sound"))]
+    pub fn play(&self, _effect: Effect) {}
+    #[cfg(feature = "sound")]
+    pub fn play(&self, effect: Effect) {
+        if let Some(loaded) = &self.loaded {
+            play_sound_once(match effect {
+                Effect::Move => &loaded.move_sound,
+                Effect::Capture => &loaded.capture,
+                Effect::Check => &loaded.check,
+                Effect::GameEnd => &loaded.game_end,
+            });
+        }
+    }
+}
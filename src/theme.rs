@@ -0,0 +1,38 @@
+use macroquad::prelude::*;
+
+// the colors the board and panels are drawn in, the highlights on top stay the same
+pub struct Theme {
+    pub background: Color,
+    pub panel: Color,
+    pub light_square: Color,
+    pub dark_square: Color,
+    pub selected: Color,
+}
+
+// cycled with T, the first is the default
+pub const THEMES: [Theme; 3] = [
+    // brown
+    Theme {
+        background: color_u8!(0x16, 0x14, 0x12, 0xff),
+        panel: color_u8!(0x26, 0x24, 0x21, 0xff),
+        light_square: color_u8!(0xf0, 0xd9, 0xb5, 0xff),
+        dark_square: color_u8!(0xb5, 0x88, 0x63, 0xff),
+        selected: color_u8!(20, 85, 30, 0x7f),
+    },
+    // blue
+    Theme {
+        background: color_u8!(0x12, 0x15, 0x1a, 0xff),
+        panel: color_u8!(0x21, 0x25, 0x2b, 0xff),
+        light_square: color_u8!(0xde, 0xe3, 0xe6, 0xff),
+        dark_square: color_u8!(0x8c, 0xa2, 0xad, 0xff),
+        selected: color_u8!(20, 85, 30, 0x7f),
+    },
+    // green
+    Theme {
+        background: color_u8!(0x14, 0x16, 0x12, 0xff),
+        panel: color_u8!(0x24, 0x26, 0x21, 0xff),
+        light_square: color_u8!(0xee, 0xee, 0xd2, 0xff),
+        dark_square: color_u8!(0x76, 0x96, 0x56, 0xff),
+        selected: color_u8!(20, 60, 110, 0x7f),
+    },
+];
@@ -0,0 +1,209 @@
+use std::io::{BufRead, Write};
+
+use crate::ai;
+use crate::game::{Color, Game, Move};
+
+// thinking time for a bare `go`, which gives neither a depth, a move time nor the clocks
+const DEFAULT_MOVETIME_MILLIS: u64 = 1000;
+// with only the clocks to go on, spend this fraction of the remaining time on each move
+const MOVES_TO_GO: u64 = 30;
+
+// the engine side of the uci protocol, one command line in and the replies out. the search
+// runs to completion before replying, so `stop` has nothing to interrupt
+#[derive(Default)]
+pub struct UciEngine {
+    game: Game,
+}
+
+impl UciEngine {
+    // the lines to send back, None once told to quit. unknown commands are ignored like the
+    // protocol asks
+    pub fn handle(&mut self, line: &str) -> Option<Vec<String>> {
+        let mut tokens = line.split_whitespace();
+        let replies = match tokens.next() {
+            Some("uci") => vec![
+                "id name chess".to_string(),
+                "id author machineonamission".to_string(),
+                "uciok".to_string(),
+            ],
+            Some("isready") => vec!["readyok".to_string()],
+            Some("ucinewgame") => {
+                self.game = Game::default();
+                vec![]
+            }
+            Some("position") => self.position(&tokens.collect::<Vec<_>>()),
+            Some("go") => self.go(&tokens.collect::<Vec<_>>()),
+            Some("quit") => return None,
+            _ => vec![],
+        };
+        Some(replies)
+    }
+    // `position startpos|fen <fen> [moves <move>...]`
+    fn position(&mut self, args: &[&str]) -> Vec<String> {
+        let moves_at = args
+            .iter()
+            .position(|a| *a == "moves")
+            .unwrap_or(args.len());
+        let game = match args.first() {
+            Some(&"startpos") => Ok(Game::default()),
+            Some(&"fen") => Game::from_fen(&args[1..moves_at].join(" ")),
+            _ => return vec!["info string expected startpos or fen".to_string()],
+        };
+        self.game = match game {
+            Ok(g) => g,
+            Err(e) => return vec![format!("info string invalid fen: {}", e)],
+        };
+        for text in args.iter().skip(moves_at + 1) {
+            let played = self.game.parse_uci(text).is_some_and(|m| {
+                self.game
+                    .request_move_promoting(&m.from, &m.target(), m.promotion)
+            });
+            // the rest of the line was played from a position we don't have
+            if !played {
+                return vec![format!("info string illegal move {}", text)];
+            }
+        }
+        vec![]
+    }
+    // `go depth <plies>`, `go movetime <millis>` or the clocks, always answered with bestmove
+    fn go(&mut self, args: &[&str]) -> Vec<String> {
+        let value = |name: &str| -> Option<u64> {
+            let at = args.iter().position(|a| *a == name)?;
+            args.get(at + 1)?.parse().ok()
+        };
+        let (time, increment) = if self.game.turn == Color::White {
+            (value("wtime"), value("winc"))
+        } else {
+            (value("btime"), value("binc"))
+        };
+        let best = if let Some(depth) = value("depth") {
+            ai::best_move(&mut self.game, depth.clamp(1, u8::MAX as u64) as u8)
+        } else {
+            let millis = value("movetime")
+                .or_else(|| time.map(|t| t / MOVES_TO_GO + increment.unwrap_or(0) / 2))
+                .unwrap_or(DEFAULT_MOVETIME_MILLIS);
+            ai::best_move_timed(&mut self.game, millis)
+        };
+        // 0000 is the null move, what uci sends when there's nothing to play
+        vec![format!(
+            "bestmove {}",
+            best.as_ref().map_or("0000".to_string(), Move::to_uci)
+        )]
+    }
+}
+
+// talks uci over input and output until quit or the end of input
+pub fn run(input: impl BufRead, mut output: impl Write) -> std::io::Result<()> {
+    let mut engine = UciEngine::default();
+    for line in input.lines() {
+        let Some(replies) = engine.handle(&line?) else {
+            break;
+        };
+        for reply in replies {
+            writeln!(output, "{}", reply)?;
+        }
+        // guis wait on each reply, don't leave it sitting in a buffer
+        output.flush()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the replies to a script of commands, one per line
+    fn exchange(script: &str) -> Vec<String> {
+        let mut output = vec![];
+        run(script.as_bytes(), &mut output).unwrap();
+        String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+
+    fn play(fen: &str, moves: &[&str]) -> Game {
+        let mut game = Game::from_fen(fen).unwrap();
+        for text in moves {
+            let m = game.parse_uci(text).unwrap();
+            assert!(game.request_move_promoting(&m.from, &m.target(), m.promotion));
+        }
+        game
+    }
+
+    // a bestmove line naming a legal move in game
+    fn assert_legal_bestmove(reply: &str, game: &Game) {
+        let mov = reply
+            .strip_prefix("bestmove ")
+            .unwrap_or_else(|| panic!("expected bestmove, got {}", reply));
+        assert!(game.parse_uci(mov).is_some(), "{} isn't legal here", mov);
+    }
+
+    const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    #[test]
+    fn scripted_exchange() {
+        let replies = exchange(
+            "uci\nisready\nucinewgame\nposition startpos moves e2e4 e7e5\ngo depth 2\nquit\n",
+        );
+        assert_eq!(
+            replies[..4],
+            [
+                "id name chess",
+                "id author machineonamission",
+                "uciok",
+                "readyok"
+            ]
+        );
+        assert_eq!(replies.len(), 5);
+        assert_legal_bestmove(&replies[4], &play(START, &["e2e4", "e7e5"]));
+    }
+
+    #[test]
+    fn fen_position_and_movetime() {
+        let fen = "r1bqkbnr/pppp1ppp/2n5/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 2 3";
+        let replies = exchange(&format!("position fen {}\ngo movetime 100\n", fen));
+        assert_eq!(replies.len(), 1);
+        assert_legal_bestmove(&replies[0], &play(fen, &[]));
+        // scholar's mate is there to be had
+        assert_eq!(replies[0], "bestmove h5f7");
+    }
+
+    #[test]
+    fn no_moves_is_the_null_move() {
+        let replies = exchange("position fen 7k/5Q2/6K1/8/8/8/8/8 b - - 0 1\ngo depth 2\n");
+        assert_eq!(replies, ["bestmove 0000"]);
+    }
+
+    #[test]
+    fn bad_positions_dont_stop_the_engine() {
+        let bad = [
+            "position fen P7/8/8/8/8/8/8/k6K w - - 0 1",
+            "position fen 8/8/8/8/8/8/8/8 w - - 0 1",
+            "position fen k7/8/8/8/8/8/8/R6K w - - 0 1",
+            "position fen garbage",
+            "position fen",
+            "position fen moves e2e4",
+            "position",
+            "position moves e2e4",
+            "position startpos moves e2e5",
+            "position startpos moves e2",
+            "position startpos moves \u{e9}\u{e9}\u{e9}\u{e9}",
+            "go depth 0",
+            "go depth x",
+        ];
+        let mut script = String::new();
+        for command in bad {
+            script.push_str(command);
+            script.push_str("\nisready\n");
+        }
+        script.push_str("position startpos\ngo depth 1\n");
+        let replies = exchange(&script);
+        assert_eq!(
+            replies.iter().filter(|r| *r == "readyok").count(),
+            bad.len()
+        );
+        assert_legal_bestmove(replies.last().unwrap(), &Game::default());
+    }
+}